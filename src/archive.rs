@@ -0,0 +1,198 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{mpsc, Condvar, Mutex, PoisonError},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+
+use crate::StoreOptions;
+
+lazy_static! {
+    /// Directories sent to the compression thread, which it has not finished with yet.
+    static ref IN_FLIGHT: (Mutex<HashSet<PathBuf>>, Condvar) = Default::default();
+}
+
+/// Mark `dir` as finished with by the compression thread, whether it was
+/// compressed or not, waking up the stores waiting to reopen it.
+pub(crate) fn finished(dir: &Path) {
+    let (in_flight, done) = &*IN_FLIGHT;
+    in_flight
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(dir);
+    done.notify_all();
+}
+
+#[derive(Debug)]
+/// Directories waiting to be compressed once their grace period has passed.
+///
+/// A directory is queued when a store moves on to a different directory, along
+/// with the end of the last time slot written into it. It is sent to the
+/// compression thread once the latest timestamp stored is at least the grace
+/// period past that end, and enough directories with later data exist, so that
+/// late frames can still be written into it.
+///
+/// The queue is not persisted. Stores recover it when they are created, from
+/// the uncompressed directories found in their root directory.
+pub(crate) struct ArchiveQueue {
+    tx: Option<mpsc::Sender<Option<PathBuf>>>,
+    grace: TimeDelta,
+    newer: usize,
+    pub(crate) pending: Vec<(PathBuf, DateTime<Utc>)>,
+}
+
+impl ArchiveQueue {
+    /// Create a queue sending directories to the compression thread `tx`, if any,
+    /// after the grace period set in `options`.
+    pub(crate) fn new(tx: Option<mpsc::Sender<Option<PathBuf>>>, options: &StoreOptions) -> Self {
+        Self {
+            tx,
+            grace: options.grace_period,
+            newer: options.grace_directories,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Whether directories are compressed at all.
+    pub(crate) fn enabled(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    /// Queue `dir`, whose last time slot written to ends at `end`.
+    pub(crate) fn push(&mut self, dir: PathBuf, end: DateTime<Utc>) {
+        if self.tx.is_none() {
+            return;
+        }
+        match self.pending.iter_mut().find(|(pending, _)| *pending == dir) {
+            Some((_, last)) => *last = end.max(*last),
+            None => self.pending.push((dir, end)),
+        }
+    }
+
+    /// Take `dir` back out of the queue before writing into it again,
+    /// restoring it from its tarball if it has already been compressed.
+    ///
+    /// If `dir` has been sent to the compression thread, this blocks until the
+    /// thread has finished with it.
+    pub(crate) fn reopen(&mut self, dir: &Path) -> Result<(), std::io::Error> {
+        self.pending.retain(|(pending, _)| pending != dir);
+        let (in_flight, done) = &*IN_FLIGHT;
+        let in_flight = in_flight.lock().unwrap_or_else(PoisonError::into_inner);
+        drop(
+            done.wait_while(in_flight, |in_flight| in_flight.contains(dir))
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        let tarball = dir.with_extension("tar.gz");
+        if dir.exists() || !tarball.exists() {
+            return Ok(());
+        }
+        log::debug!("Restoring {dir:?} from {tarball:?}");
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&tarball)?));
+        archive.unpack(dir.parent().unwrap_or(Path::new(".")))?;
+        std::fs::remove_file(tarball)
+    }
+
+    /// Send the directories whose grace period has passed to the compression thread.
+    ///
+    /// # Arguments:
+    /// - `latest`: Latest timestamp stored.
+    /// - `current`: End of the time slot being written to, in the current directory.
+    pub(crate) fn release(&mut self, latest: DateTime<Utc>, current: DateTime<Utc>) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let ends = self.pending.iter().map(|(_, end)| *end).collect::<Vec<_>>();
+        let newer = |end: DateTime<Utc>| {
+            ends.iter().filter(|other| **other > end).count() + usize::from(current > end)
+        };
+        self.pending.retain(|(dir, end)| {
+            if *end + self.grace > latest || newer(*end) < self.newer {
+                return true;
+            }
+            IN_FLIGHT
+                .0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(dir.clone());
+            if tx.send(Some(dir.clone())).is_err() {
+                finished(dir);
+            }
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::utils::compressor;
+
+    #[test]
+    fn test_archive_queue() {
+        let root = "test_archive_queue";
+        std::fs::remove_dir_all(root).unwrap_or_default();
+        let dir = Path::new(root).join("20250101");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("data.json"), b"[]").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let hdl = compressor(rx);
+        let options = StoreOptions::new()
+            .grace_period(TimeDelta::hours(2))
+            .grace_directories(2);
+        let mut queue = ArchiveQueue::new(Some(tx.clone()), &options);
+        let end = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap();
+        let day = TimeDelta::days(1);
+        queue.push(dir.clone(), end);
+        queue.release(end + TimeDelta::hours(1), end + day);
+        assert_eq!(queue.pending.len(), 1);
+        // a single newer directory
+        queue.release(end + TimeDelta::hours(2), end + day);
+        assert_eq!(queue.pending.len(), 1);
+        queue.push(Path::new(root).join("20250102"), end + day);
+        queue.release(end + TimeDelta::hours(2), end + day * 2);
+        assert_eq!(queue.pending.len(), 1);
+        tx.send(None).unwrap();
+        hdl.join().unwrap();
+        assert!(!dir.exists());
+        assert!(dir.with_extension("tar.gz").exists());
+
+        // a late frame restores the directory
+        queue.reopen(&dir).unwrap();
+        assert_eq!(std::fs::read(dir.join("data.json")).unwrap(), b"[]");
+        assert!(!dir.with_extension("tar.gz").exists());
+        std::fs::remove_dir_all(root).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_reopen_in_flight() {
+        let root = "test_reopen_in_flight";
+        std::fs::remove_dir_all(root).unwrap_or_default();
+        let dir = Path::new(root).join("20250101");
+        std::fs::create_dir_all(&dir).unwrap();
+        for i in 0..100 {
+            std::fs::write(dir.join(format!("{i}.bin")), vec![i as u8; 64 * 1024]).unwrap();
+        }
+        let (tx, rx) = mpsc::channel();
+        let hdl = compressor(rx);
+        let mut queue = ArchiveQueue::new(Some(tx.clone()), &StoreOptions::new());
+        let end = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap();
+        queue.push(dir.clone(), end);
+        queue.release(end, end + TimeDelta::days(1));
+        assert!(queue.pending.is_empty());
+        // a frame backfilled right after the directory is released
+        queue.reopen(&dir).unwrap();
+        std::fs::write(dir.join("late.bin"), b"late").unwrap();
+        tx.send(None).unwrap();
+        hdl.join().unwrap();
+        assert_eq!(std::fs::read(dir.join("late.bin")).unwrap(), b"late");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 101);
+        assert!(!dir.with_extension("tar.gz").exists());
+        std::fs::remove_dir_all(root).unwrap_or_default();
+    }
+}
@@ -0,0 +1,93 @@
+use std::{fs::File, io::Write, path::PathBuf};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::SlotLength;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Summary of the files touched by a batch store.
+pub struct BatchSummary {
+    /// Files written to, in order, along with the number of frames
+    /// written to each. A file appears more than once if the batch
+    /// moves back and forth between time slots.
+    pub files: Vec<(PathBuf, usize)>,
+}
+
+impl BatchSummary {
+    /// Total number of frames stored.
+    pub fn frames(&self) -> usize {
+        self.files.iter().map(|(_, count)| count).sum()
+    }
+}
+
+pub(crate) trait BatchStore {
+    /// Time zone of the time slots.
+    type Tz: TimeZone;
+    /// Length and time zone of the time slots, identifying the file of a timestamp.
+    fn slots(&self) -> (SlotLength, Self::Tz);
+    /// Route a frame to its file, and get the writer for that file.
+    fn route(&mut self, tstamp: DateTime<Utc>) -> Result<(PathBuf, &mut File), std::io::Error>;
+    /// Register and index the frames just written to the current file, given their
+    /// lengths and timestamps.
+    fn commit_frames(&mut self, frames: &[(u64, DateTime<Utc>)]) -> Result<(), std::io::Error>;
+
+    /// Store consecutive frames belonging to the same file with a single
+    /// write, which is flushed (and optionally synced) once before moving on
+    /// to the next file.
+    ///
+    /// Each frame is encoded into memory before anything is written, so that
+    /// only complete frames are stored. If a frame cannot be encoded, the
+    /// complete frames preceding it in its file are stored and indexed before
+    /// the error is returned.
+    ///
+    /// `encode` is told whether the frame is the first frame of the file.
+    fn store_batch_with<I, D, E>(
+        &mut self,
+        frames: I,
+        sync: bool,
+        mut encode: E,
+    ) -> Result<BatchSummary, std::io::Error>
+    where
+        I: IntoIterator<Item = (DateTime<Utc>, D)>,
+        E: FnMut(&mut Vec<u8>, D, bool) -> Result<(), std::io::Error>,
+    {
+        let mut summary = BatchSummary::default();
+        let (slot, tz) = self.slots();
+        let mut frames = frames.into_iter().peekable();
+        let mut failed = None;
+        while let Some((tstamp, data)) = frames.next() {
+            let key = slot.start(&tstamp.with_timezone(&tz));
+            let (path, writer) = self.route(tstamp)?;
+            let mut first = writer.metadata()?.len() == 0;
+            // encoded frames of this file, with their lengths and timestamps
+            let mut buffer = Vec::new();
+            let mut written = Vec::new();
+            let mut frame = Some((tstamp, data));
+            while let Some((tstamp, data)) = frame.take().or_else(|| {
+                frames.next_if(|(tstamp, _)| slot.start(&tstamp.with_timezone(&tz)) == key)
+            }) {
+                let start = buffer.len();
+                if let Err(e) = encode(&mut buffer, data, first) {
+                    buffer.truncate(start); // drop the partial frame
+                    failed = Some(e);
+                    break;
+                }
+                first = false;
+                written.push(((buffer.len() - start) as u64, tstamp));
+            }
+            if !written.is_empty() {
+                writer.write_all(&buffer)?;
+                writer.flush()?;
+                if sync {
+                    writer.sync_data()?;
+                }
+                self.commit_frames(&written)?;
+                summary.files.push((path, written.len()));
+            }
+            if let Some(e) = failed {
+                return Err(e);
+            }
+        }
+        Ok(summary)
+    }
+}
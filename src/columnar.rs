@@ -0,0 +1,205 @@
+use std::{
+    fs::{rename, File},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow_array::RecordBatch;
+use arrow_schema::{FieldRef, Schema};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    basic::Compression,
+    file::{metadata::KeyValue, properties::WriterProperties},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_arrow::{
+    schema::{SchemaLike, TracingOptions},
+    ArrayBuilder,
+};
+
+use crate::{framewriter::partial_path, utils::check_header, FileHeader, FmtInfo};
+
+/// Default number of data frames per row group.
+pub(crate) const ROW_GROUP_SIZE: usize = 10_000;
+
+/// Key of the Parquet file metadata entry containing the [FileHeader] as JSON.
+const HEADER_KEY: &str = "datastor.header";
+
+#[derive(Debug)]
+/// Parquet data store marker.
+///
+/// Data frames are collected into row groups, and written to a hidden
+/// temporary file next to the data file. The file is closed and moved into
+/// place when the store moves on to the next time slot, or when the store is
+/// dropped. If the data file already exists, e.g. after a restart, its rows
+/// are copied into the new file first. The same goes for a temporary file
+/// left behind by a failed close, which already holds the rows of the data
+/// file.
+///
+/// The columns are derived from the fields of `T` using its [Deserialize](serde::Deserialize)
+/// implementation, so `T` must implement both [Serialize] and [Deserialize](serde::Deserialize).
+/// The [FileHeader] is stored as JSON in the file metadata under the `datastor.header` key.
+pub struct Parquet<T> {
+    _marker: PhantomData<T>,
+}
+
+impl<T> FmtInfo for Parquet<T> {
+    fn delimiter() -> &'static [u8] {
+        b""
+    }
+
+    fn extension() -> &'static str {
+        "parquet"
+    }
+
+    fn initialize<W: std::io::Write>(writer: W, _progname: &str) -> std::io::Result<W> {
+        // the file is written by the store
+        Ok(writer)
+    }
+
+    fn schema_name() -> Option<String> {
+        Some(std::any::type_name::<T>().to_owned())
+    }
+}
+
+/// Parquet file being written by a store.
+pub(crate) struct ParquetFile {
+    key: String,
+    path: PathBuf,
+    tmp_path: PathBuf,
+    builder: ArrayBuilder,
+    rows: usize,
+    row_group_size: usize,
+    writer: ArrowWriter<File>,
+    closed: bool,
+}
+
+impl ParquetFile {
+    /// Start writing the data file `path` of the time slot `key`.
+    ///
+    /// # Errors:
+    /// - If the columns cannot be derived from `T`.
+    /// - If the existing data or temporary file cannot be read, e.g. a temporary file
+    ///   left behind by a crash, an `std::io::Error` with `InvalidData` kind is returned.
+    /// - If the existing file was written using another schema, an `std::io::Error`
+    ///   with `InvalidData` kind is returned.
+    pub(crate) fn create<T: DeserializeOwned>(
+        path: PathBuf,
+        key: String,
+        header: &FileHeader,
+        row_group_size: usize,
+    ) -> Result<Self, std::io::Error> {
+        let fields = Vec::<FieldRef>::from_type::<T>(TracingOptions::default())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+        let metadata = serde_json::to_string(header)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+        let schema = Schema::new(fields.clone());
+        let properties = WriterProperties::builder()
+            .set_key_value_metadata(Some(vec![KeyValue::new(HEADER_KEY.to_owned(), metadata)]))
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_row_count(Some(row_group_size))
+            .build();
+        let tmp_path = partial_path(&path);
+        // carry over the rows of the existing file, read before the temporary file is truncated
+        let batches = match [&tmp_path, &path].into_iter().find(|path| path.exists()) {
+            Some(existing) => read_batches(existing, header)?,
+            None => Vec::new(),
+        };
+        let mut writer =
+            ArrowWriter::try_new(File::create(&tmp_path)?, Arc::new(schema), Some(properties))
+                .map_err(std::io::Error::other)?;
+        if !batches.is_empty() {
+            for batch in batches {
+                writer
+                    .write(&batch)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            }
+            writer.flush().map_err(std::io::Error::other)?;
+        }
+        Ok(Self {
+            key,
+            path,
+            tmp_path,
+            builder: ArrayBuilder::from_arrow(&fields)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?,
+            rows: 0,
+            row_group_size,
+            writer,
+            closed: false,
+        })
+    }
+
+    /// Time slot of the data file.
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Path of the data file.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add a record, writing a row group once enough records are collected.
+    pub(crate) fn push<T: Serialize>(&mut self, record: &T) -> Result<(), std::io::Error> {
+        self.builder
+            .push(record)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+        self.rows += 1;
+        if self.rows >= self.row_group_size {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn write_row_group(&mut self) -> Result<(), std::io::Error> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let batch = self
+            .builder
+            .to_record_batch()
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+        self.rows = 0;
+        self.writer.write(&batch).map_err(std::io::Error::other)?;
+        self.writer.flush().map_err(std::io::Error::other)
+    }
+
+    /// Write the remaining records, close the file and move it into place.
+    ///
+    /// If this fails, the file can be finished again to retry.
+    pub(crate) fn finish(&mut self) -> Result<PathBuf, std::io::Error> {
+        if !self.closed {
+            self.write_row_group()?;
+            self.writer.finish().map_err(std::io::Error::other)?;
+            self.closed = true;
+        }
+        self.writer.inner().sync_all()?;
+        rename(&self.tmp_path, &self.path)?;
+        Ok(self.path.clone())
+    }
+}
+
+/// Read the rows of the existing Parquet file `path`, making sure it was written
+/// using the schema in `header`.
+fn read_batches(path: &Path, header: &FileHeader) -> Result<Vec<RecordBatch>, std::io::Error> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    let existing = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == HEADER_KEY))
+        .and_then(|kv| kv.value.as_deref())
+        .map(serde_json::from_str::<FileHeader>)
+        .transpose()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    if let Some(existing) = existing {
+        check_header(path, &existing, header)?;
+    }
+    builder
+        .build()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?
+        .map(|batch| batch.map_err(std::io::Error::other))
+        .collect()
+}
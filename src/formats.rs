@@ -1,18 +1,180 @@
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     hash::{DefaultHasher, Hash, Hasher},
-    io::Write,
+    io::{IoSlice, Write},
     marker::PhantomData,
+    time::Duration,
 };
 
 #[allow(unused_imports)]
 use crate::UtcHourly;
+use crate::{utils::write_all_vectored, StoreOptions};
 
 /// Binary data version string.
 pub const BINARY_VERSION: &str = "1.0";
 const BINARY_ALIGN: u32 = size_of::<u32>() as _;
 const BINARY_PADDING: [u8; BINARY_ALIGN as usize] = [0xff; BINARY_ALIGN as usize];
 const BINARY_FRAME_START: &[u8] = b"FRME";
+const BINARY_FRAME_START_64: &[u8] = b"FR64";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Version of the binary frame format.
+pub enum BinaryVersion {
+    #[default]
+    #[serde(rename = "1.0")]
+    /// Version 1.0: frame and payload sizes are stored as [u32], limiting
+    /// payloads to just under 4 GiB.
+    V1,
+    #[serde(rename = "2.0")]
+    /// Version 2.0: frame and payload sizes are stored as [u64], and frames
+    /// start with `FR64` instead of `FRME`.
+    V2,
+}
+
+impl BinaryVersion {
+    /// Version string, as written in the file header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryVersion::V1 => BINARY_VERSION,
+            BinaryVersion::V2 => "2.0",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Compression codec applied to the payload of each binary frame.
+///
+/// The codec is recorded in the header of the data file. Compressing and
+/// decompressing requires the `zstd` or `lz4` feature respectively.
+pub enum Codec {
+    /// Zstandard, with the given compression level.
+    Zstd(i32),
+    /// LZ4 block format, prefixed with the uncompressed size.
+    Lz4,
+}
+
+impl Codec {
+    /// Compress a frame payload.
+    ///
+    /// # Errors:
+    /// - If the codec is not enabled, an `std::io::Error` with `Unsupported` kind is returned.
+    /// - If the payload cannot be compressed.
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Codec::Zstd(level) => zstd::bulk::compress(data, *level),
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = data;
+                Err(self.unsupported())
+            }
+        }
+    }
+
+    /// Decompress a frame payload.
+    ///
+    /// # Errors:
+    /// - If the codec is not enabled, an `std::io::Error` with `Unsupported` kind is returned.
+    /// - If the payload cannot be decompressed, an `std::io::Error` with `InvalidData` kind is returned.
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Codec::Zstd(_) => zstd::stream::decode_all(data)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = data;
+                Err(self.unsupported())
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    fn unsupported(&self) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("Codec {self:?} is not enabled"),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+/// Time slot covered by a data file.
+pub enum TimeSlot {
+    /// Slot bounded by wall clock times.
+    Utc {
+        /// Start of the slot (inclusive).
+        start: DateTime<Utc>,
+        /// End of the slot (exclusive).
+        end: DateTime<Utc>,
+    },
+    /// Slot bounded by the time elapsed since the start of execution.
+    Elapsed {
+        /// Start of the slot (inclusive).
+        start: Duration,
+        /// End of the slot (exclusive).
+        end: Duration,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Information about a data file.
+///
+/// The header is handed to [FmtInfo::initialize_with] when a new file is
+/// created. [Binary] files store it as JSON in their first frame, and [Json]
+/// files in their first line. It can be read back using [read_header](crate::read_header).
+pub struct FileHeader {
+    /// Binary frame format version of the data file.
+    pub version: BinaryVersion,
+    /// File format, given by the file extension.
+    pub format: String,
+    /// Program that is writing the data file.
+    pub progname: String,
+    /// Creation time of the data file.
+    pub created: DateTime<Utc>,
+    /// Type of the store that created the data file.
+    pub store: String,
+    /// Time slot covered by the data file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<TimeSlot>,
+    /// Compression codec of the binary frame payloads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<Codec>,
+    /// User supplied identifier of the schema of the data frames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// Version of the schema of the data frames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    /// User metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl FileHeader {
+    pub(crate) fn new<Kind: FmtInfo>(progname: &str, store: &str) -> Self {
+        Self {
+            version: BinaryVersion::default(),
+            format: Kind::extension().to_owned(),
+            progname: progname.to_owned(),
+            created: Utc::now(),
+            store: store.to_owned(),
+            slot: None,
+            codec: None,
+            schema: None,
+            schema_version: None,
+            metadata: None,
+        }
+    }
+}
 
 /// This trait contains functions that describe the file extension,
 /// inter-frame delimiter and header initializer for a data storage
@@ -31,12 +193,116 @@ pub trait FmtInfo {
     fn initialize<W>(writer: W, progname: &str) -> std::io::Result<W>
     where
         W: Write;
+    /// Initialize a new file using the full header information.
+    ///
+    /// The default implementation calls [FmtInfo::initialize].
+    ///
+    /// # Arguments
+    /// - `writer`: Where header data is written
+    /// - `header`: Information about the new data file
+    fn initialize_with<W>(writer: W, header: &FileHeader) -> std::io::Result<W>
+    where
+        W: Write,
+    {
+        Self::initialize(writer, &header.progname)
+    }
     /// Hash of the type name
     fn type_hash() -> u64 {
         let mut hasher = DefaultHasher::new();
         std::any::type_name::<Self>().hash(&mut hasher);
         hasher.finish()
     }
+    /// Whether data frames are stored in binary frames, whose payloads
+    /// are compressed with the [Codec] set in the [StoreOptions].
+    fn framed() -> bool {
+        false
+    }
+    /// Schema name recorded in the file header, unless one is
+    /// set in the [StoreOptions](crate::StoreOptions).
+    fn schema_name() -> Option<String> {
+        None
+    }
+}
+
+/// Encoding of the data frames of a data storage type.
+///
+/// Every store has a `store` method for data types implementing this trait,
+/// so user-defined formats can be stored the same way as the built-in ones.
+///
+/// # Example:
+/// ```rust,no_run
+/// use datastor::{Encoder, FmtInfo, StoreOptions, UtcHourly};
+/// use std::io::Write;
+///
+/// /// Comma separated samples, one line per frame.
+/// struct Samples {}
+///
+/// impl FmtInfo for Samples {
+///     fn delimiter() -> &'static [u8] {
+///         b"\n"
+///     }
+///
+///     fn extension() -> &'static str {
+///         "samples"
+///     }
+///
+///     fn initialize<W: Write>(writer: W, _progname: &str) -> std::io::Result<W> {
+///         Ok(writer)
+///     }
+/// }
+///
+/// impl Encoder for Samples {
+///     type Frame = [f32];
+///
+///     fn encode<W: Write>(
+///         writer: &mut W,
+///         frame: &[f32],
+///         _options: &StoreOptions,
+///     ) -> std::io::Result<()> {
+///         let line = frame.iter().map(f32::to_string).collect::<Vec<_>>().join(",");
+///         writer.write_all(line.as_bytes())?;
+///         writer.write_all(Self::delimiter())
+///     }
+/// }
+///
+/// let mut store = UtcHourly::<Samples>::new("test".into(), false, "testprogram").unwrap();
+/// store.store(chrono::Utc::now(), &[1.0, 2.5]).unwrap();
+/// ```
+pub trait Encoder: FmtInfo {
+    /// Type of the data frames.
+    type Frame: ?Sized;
+    /// Encode a data frame, including any framing and delimiter.
+    ///
+    /// # Arguments
+    /// - `writer`: Where the encoded frame is written
+    /// - `frame`: Data frame to be encoded
+    /// - `options`: Options of the store
+    fn encode<W: Write>(
+        writer: &mut W,
+        frame: &Self::Frame,
+        options: &StoreOptions,
+    ) -> std::io::Result<()>;
+    /// Encode the first data frame of a file, written right after the file is
+    /// initialized, e.g. to precede it with a header derived from the frame.
+    ///
+    /// The default implementation calls [Encoder::encode].
+    fn encode_first<W: Write>(
+        writer: &mut W,
+        frame: &Self::Frame,
+        options: &StoreOptions,
+    ) -> std::io::Result<()> {
+        Self::encode(writer, frame, options)
+    }
+    /// Encode a data frame stored in its own file by a single frame store.
+    ///
+    /// The default implementation calls [Encoder::encode].
+    fn encode_single<W: Write>(
+        writer: &mut W,
+        frame: &Self::Frame,
+        options: &StoreOptions,
+    ) -> std::io::Result<()> {
+        Self::encode(writer, frame, options)
+    }
 }
 
 #[derive(Debug)]
@@ -52,19 +318,39 @@ impl FmtInfo for Binary {
         "bin"
     }
 
+    fn framed() -> bool {
+        true
+    }
+
     fn initialize<W: Write>(writer: W, progname: &str) -> std::io::Result<W> {
-        let fmt = format!(
-            "
-# This file is generated by {progname} using binary format version {BINARY_VERSION}.
-# The binary file is aligned to 4 byte boundaries.
-# The file format is described as follows:
-# <Ofst 0: Frame size (4 LE bytes, aligned to 4 bytes)>
-# <Ofst 4: Payload size (4 LE bytes)>
-# <Ofst 8: Data[0..Payload Size + 1]>
-# <Ofst Payload Size + 1: Padding[FF; Payload Size % 4]>
-# <Next Frame Start>\n"
-        );
-        store_binary(writer, fmt.as_bytes())
+        Self::initialize_with(writer, &FileHeader::new::<Self>(progname, "unknown"))
+    }
+
+    fn initialize_with<W: Write>(writer: W, header: &FileHeader) -> std::io::Result<W> {
+        let repr = serde_json::to_vec(header)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+        store_binary(writer, &repr, header.version)
+    }
+}
+
+impl Encoder for Binary {
+    type Frame = [u8];
+
+    fn encode<W: Write>(
+        writer: &mut W,
+        frame: &[u8],
+        options: &StoreOptions,
+    ) -> std::io::Result<()> {
+        write_frame(writer, frame, options.binary_version, options.codec)
+    }
+
+    fn encode_single<W: Write>(
+        writer: &mut W,
+        frame: &[u8],
+        options: &StoreOptions,
+    ) -> std::io::Result<()> {
+        // single frame files have no header to record the codec in
+        write_binary(writer, frame, options.binary_version)
     }
 }
 
@@ -74,16 +360,52 @@ pub struct Json<T> {
     _marker: PhantomData<T>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 /// JSONL data file header.
 pub struct JsonHeader {
     header: String,
+    #[serde(flatten)]
+    info: FileHeader,
 }
 
 impl JsonHeader {
-    fn new(progname: &str) -> Self {
+    fn new(header: &FileHeader) -> Self {
         Self {
-            header: format!("This file is created by {progname}."),
+            header: format!("This file is created by {}.", header.progname),
+            info: header.clone(),
+        }
+    }
+
+    /// Parse the header line of a JSONL data file.
+    ///
+    /// Files written by earlier versions of this crate only have a `header` field.
+    /// Their header has no schema version, i.e. schema version 0, an unknown store,
+    /// and the Unix epoch as creation time.
+    ///
+    /// # Errors:
+    /// - If the line is not a header, an `std::io::Error` with `InvalidData` kind is returned.
+    pub(crate) fn parse(line: &str) -> Result<FileHeader, std::io::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct LegacyHeader {
+            header: String,
+        }
+
+        match serde_json::from_str::<JsonHeader>(line) {
+            Ok(header) => Ok(header.info),
+            Err(err) => match serde_json::from_str::<LegacyHeader>(line) {
+                Ok(LegacyHeader { header }) => {
+                    let progname = header
+                        .strip_prefix("This file is created by ")
+                        .and_then(|progname| progname.strip_suffix('.'))
+                        .unwrap_or_default();
+                    Ok(FileHeader {
+                        created: DateTime::UNIX_EPOCH,
+                        ..FileHeader::new::<Json<()>>(progname, "unknown")
+                    })
+                }
+                Err(_) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+            },
         }
     }
 }
@@ -97,8 +419,12 @@ impl<T> FmtInfo for Json<T> {
         "json"
     }
 
-    fn initialize<W: Write>(mut writer: W, progname: &str) -> std::io::Result<W> {
-        let repr = serde_json::to_string(&JsonHeader::new(progname))
+    fn initialize<W: Write>(writer: W, progname: &str) -> std::io::Result<W> {
+        Self::initialize_with(writer, &FileHeader::new::<Self>(progname, "unknown"))
+    }
+
+    fn initialize_with<W: Write>(mut writer: W, header: &FileHeader) -> std::io::Result<W> {
+        let repr = serde_json::to_string(&JsonHeader::new(header))
             .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
         writer.write_all(repr.as_bytes())?;
         writer.write_all(b"\n")?;
@@ -112,33 +438,388 @@ impl<T> FmtInfo for Json<T> {
         "JSONL".hash(&mut hasher);
         hasher.finish()
     }
+
+    fn schema_name() -> Option<String> {
+        Some(std::any::type_name::<T>().to_owned())
+    }
+}
+
+impl<T: Serialize> Encoder for Json<T> {
+    type Frame = T;
+
+    fn encode<W: Write>(writer: &mut W, frame: &T, options: &StoreOptions) -> std::io::Result<()> {
+        Self::encode_single(writer, frame, options)?;
+        writer.write_all(Self::delimiter())
+    }
+
+    fn encode_single<W: Write>(
+        writer: &mut W,
+        frame: &T,
+        _options: &StoreOptions,
+    ) -> std::io::Result<()> {
+        serde_json::to_writer(writer, frame)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+    }
+}
+
+/// Compact serialized data formats.
+///
+/// Each data frame is serialized and stored in a binary frame, following
+/// a header frame containing the [FileHeader] as JSON, as in [Binary] files.
+/// The markers implementing this trait are [MsgPack] (feature `msgpack`),
+/// [Cbor] (feature `cbor`) and [Postcard] (feature `postcard`).
+pub trait SerdeFmt: FmtInfo {
+    /// Type of the data frames.
+    type Frame: Serialize + DeserializeOwned;
+    /// Serialize a data frame.
+    fn to_bytes(frame: &Self::Frame) -> std::io::Result<Vec<u8>>;
+    /// Deserialize a data frame.
+    fn from_bytes(data: &[u8]) -> std::io::Result<Self::Frame>;
+}
+
+macro_rules! serde_fmt {
+    ($(#[$meta:meta])* $name:ident, $feature:literal, $extension:literal, $encode:path, $decode:path) => {
+        #[cfg(feature = $feature)]
+        #[derive(Debug)]
+        $(#[$meta])*
+        pub struct $name<T> {
+            _marker: PhantomData<T>,
+        }
+
+        #[cfg(feature = $feature)]
+        impl<T> FmtInfo for $name<T> {
+            fn delimiter() -> &'static [u8] {
+                b""
+            }
+
+            fn extension() -> &'static str {
+                $extension
+            }
+
+            fn framed() -> bool {
+                true
+            }
+
+            fn initialize<W: Write>(writer: W, progname: &str) -> std::io::Result<W> {
+                Self::initialize_with(writer, &FileHeader::new::<Self>(progname, "unknown"))
+            }
+
+            fn initialize_with<W: Write>(writer: W, header: &FileHeader) -> std::io::Result<W> {
+                Binary::initialize_with(writer, header)
+            }
+
+            fn schema_name() -> Option<String> {
+                Some(std::any::type_name::<T>().to_owned())
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<T: Serialize + DeserializeOwned> SerdeFmt for $name<T> {
+            type Frame = T;
+
+            fn to_bytes(frame: &T) -> std::io::Result<Vec<u8>> {
+                $encode(frame).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+            }
+
+            fn from_bytes(data: &[u8]) -> std::io::Result<T> {
+                $decode(data).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<T: Serialize + DeserializeOwned> Encoder for $name<T> {
+            type Frame = T;
+
+            fn encode<W: Write>(writer: &mut W, frame: &T, options: &StoreOptions) -> std::io::Result<()> {
+                write_frame(writer, &Self::to_bytes(frame)?, options.binary_version, options.codec)
+            }
+
+            fn encode_single<W: Write>(writer: &mut W, frame: &T, options: &StoreOptions) -> std::io::Result<()> {
+                // single frame files have no header to record the codec in
+                write_binary(writer, &Self::to_bytes(frame)?, options.binary_version)
+            }
+        }
+    };
+}
+
+serde_fmt!(
+    /// MessagePack data store marker.
+    MsgPack,
+    "msgpack",
+    "msgpack",
+    rmp_serde::to_vec_named,
+    rmp_serde::from_slice
+);
+
+serde_fmt!(
+    /// CBOR data store marker.
+    Cbor,
+    "cbor",
+    "cbor",
+    cbor_to_vec,
+    cbor_from_slice
+);
+
+serde_fmt!(
+    /// Postcard data store marker.
+    Postcard,
+    "postcard",
+    "postcard",
+    postcard::to_allocvec,
+    postcard::from_bytes
+);
+
+#[cfg(feature = "cbor")]
+fn cbor_to_vec<T: Serialize>(frame: &T) -> Result<Vec<u8>, ciborium::ser::Error<std::io::Error>> {
+    let mut data = Vec::new();
+    ciborium::into_writer(frame, &mut data)?;
+    Ok(data)
+}
+
+#[cfg(feature = "cbor")]
+fn cbor_from_slice<T: DeserializeOwned>(
+    data: &[u8],
+) -> Result<T, ciborium::de::Error<std::io::Error>> {
+    ciborium::from_reader(data)
+}
+
+#[cfg(feature = "csv")]
+#[derive(Debug)]
+/// CSV data store marker.
+///
+/// Each data frame is stored as a row. The first row of a file is preceded
+/// by a header row containing the field names serialized by the [Serialize]
+/// implementation of `T`. Only flat records can be stored, i.e. structs whose
+/// fields are scalars or strings. Other shapes, such as structs with
+/// `#[serde(flatten)]` fields, are rejected with an `std::io::Error` of
+/// `InvalidInput` kind.
+///
+/// CSV files do not contain a [FileHeader].
+pub struct Csv<T> {
+    _marker: PhantomData<T>,
+}
+
+#[cfg(feature = "csv")]
+impl<T> FmtInfo for Csv<T> {
+    fn delimiter() -> &'static [u8] {
+        b"\n"
+    }
+
+    fn extension() -> &'static str {
+        "csv"
+    }
+
+    fn initialize<W: Write>(writer: W, _progname: &str) -> std::io::Result<W> {
+        // the header row is written along with the first row
+        Ok(writer)
+    }
+
+    fn schema_name() -> Option<String> {
+        Some(std::any::type_name::<T>().to_owned())
+    }
+}
+
+#[cfg(feature = "csv")]
+impl<T: Serialize> Encoder for Csv<T> {
+    type Frame = T;
+
+    fn encode<W: Write>(writer: &mut W, frame: &T, _options: &StoreOptions) -> std::io::Result<()> {
+        write_csv(writer, frame, false)
+    }
+
+    fn encode_first<W: Write>(
+        writer: &mut W,
+        frame: &T,
+        _options: &StoreOptions,
+    ) -> std::io::Result<()> {
+        write_csv(writer, frame, true)
+    }
+
+    fn encode_single<W: Write>(
+        writer: &mut W,
+        frame: &T,
+        options: &StoreOptions,
+    ) -> std::io::Result<()> {
+        Self::encode_first(writer, frame, options)
+    }
+}
+
+/// Write a CSV row without flushing the writer, preceded by the header row
+/// of `data` if `headers` is set.
+#[cfg(feature = "csv")]
+pub(crate) fn write_csv<W: Write, T: Serialize>(
+    writer: W,
+    data: &T,
+    headers: bool,
+) -> std::io::Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(headers)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(writer);
+    writer
+        .serialize(data)
+        .map_err(|err| match err.into_kind() {
+            csv::ErrorKind::Io(err) => err,
+            kind => std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{kind:?}")),
+        })?;
+    writer
+        .into_inner()
+        .map(|_| ())
+        .map_err(|err| std::io::Error::other(err.to_string()))
 }
 
-pub(crate) fn store_binary<W>(mut writer: W, data: &[u8]) -> Result<W, std::io::Error>
+pub(crate) fn store_binary<W>(
+    mut writer: W,
+    data: &[u8],
+    version: BinaryVersion,
+) -> Result<W, std::io::Error>
 where
     W: Write,
 {
-    let data_size = data.len() as u32;
-    let padding = BINARY_ALIGN - (data_size % BINARY_ALIGN);
-    let frame_size = data_size
-        .checked_add(size_of::<u32>() as u32 + padding) // payload size + padding
-        .ok_or(std::io::Error::new(
-            std::io::ErrorKind::OutOfMemory,
-            "Frame size too large",
-        ))?;
-    debug_assert!(
-        frame_size % BINARY_ALIGN == 0,
-        "Frame size {frame_size} is not {BINARY_ALIGN}-byte aligned: {data_size} + {padding}"
-    );
-    writer.write_all(BINARY_FRAME_START)?; // write frame start
-    writer.write_all(&frame_size.to_le_bytes())?; // write frame size
-    writer.write_all(&data_size.to_le_bytes())?; // write header size
-    writer.write_all(data)?; // write the data
-    writer.write_all(&BINARY_PADDING[..padding as usize])?; // write the padding
+    write_binary(&mut writer, data, version)?;
     writer.flush()?;
     Ok(writer)
 }
 
+/// Write a binary frame without flushing the writer.
+pub(crate) fn write_binary<W>(
+    writer: &mut W,
+    data: &[u8],
+    version: BinaryVersion,
+) -> Result<(), std::io::Error>
+where
+    W: Write,
+{
+    let (header, padding) = binary_frame_header(data.len() as u64, version)?;
+    writer.write_all(&header)?; // write frame start, frame size and payload size
+    writer.write_all(data)?; // write the data
+    writer.write_all(&BINARY_PADDING[..padding])?; // write the padding
+    Ok(())
+}
+
+/// Write a binary frame without flushing the writer, compressing the
+/// payload with `codec`.
+pub(crate) fn write_frame<W>(
+    writer: &mut W,
+    data: &[u8],
+    version: BinaryVersion,
+    codec: Option<Codec>,
+) -> Result<(), std::io::Error>
+where
+    W: Write,
+{
+    match codec {
+        Some(codec) => write_binary(writer, &codec.compress(data)?, version),
+        None => write_binary(writer, data, version),
+    }
+}
+
+/// Store a binary frame gathered from multiple buffers, returning the
+/// number of bytes written.
+pub(crate) fn store_binary_vectored<W>(
+    mut writer: W,
+    data: &[IoSlice<'_>],
+    version: BinaryVersion,
+    codec: Option<Codec>,
+) -> Result<u64, std::io::Error>
+where
+    W: Write,
+{
+    if let Some(codec) = codec {
+        // the payload is compressed as a whole
+        let data = data
+            .iter()
+            .flat_map(|slice| slice.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        let mut frame = Vec::new();
+        write_binary(&mut frame, &codec.compress(&data)?, version)?;
+        writer.write_all(&frame)?;
+        writer.flush()?;
+        return Ok(frame.len() as u64);
+    }
+    let data_size = data.iter().map(|slice| slice.len() as u64).sum::<u64>();
+    let (header, padding) = binary_frame_header(data_size, version)?;
+    let mut slices = Vec::with_capacity(data.len() + 2);
+    slices.push(IoSlice::new(&header)); // frame start, frame size and payload size
+    slices.extend_from_slice(data); // the data
+    slices.push(IoSlice::new(&BINARY_PADDING[..padding])); // the padding
+    write_all_vectored(&mut writer, &mut slices)?;
+    writer.flush()?;
+    Ok(header.len() as u64 + data_size + padding as u64)
+}
+
+/// Placeholder header of a binary frame whose size is not known yet.
+pub(crate) fn binary_frame_placeholder(version: BinaryVersion) -> Vec<u8> {
+    match version {
+        BinaryVersion::V1 => {
+            let mut header = BINARY_FRAME_START.to_vec();
+            header.extend_from_slice(&[0; 2 * size_of::<u32>()]);
+            header
+        }
+        BinaryVersion::V2 => {
+            let mut header = BINARY_FRAME_START_64.to_vec();
+            header.extend_from_slice(&[0; 2 * size_of::<u64>()]);
+            header
+        }
+    }
+}
+
+/// Padding bytes that follow a binary frame payload of `padding` bytes.
+pub(crate) fn binary_padding(padding: usize) -> &'static [u8] {
+    &BINARY_PADDING[..padding]
+}
+
+/// Build the header of a binary frame containing `data_size` bytes of payload.
+///
+/// Returns the frame start marker followed by the frame and payload sizes,
+/// and the number of padding bytes that must follow the payload.
+pub(crate) fn binary_frame_header(
+    data_size: u64,
+    version: BinaryVersion,
+) -> Result<(Vec<u8>, usize), std::io::Error> {
+    let padding = BINARY_ALIGN as u64 - (data_size % BINARY_ALIGN as u64);
+    let header = match version {
+        BinaryVersion::V1 => {
+            let too_large = || {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "Payload of {data_size} bytes is too large for binary format version {}",
+                        version.as_str()
+                    ),
+                )
+            };
+            let frame_size = data_size
+                .checked_add(size_of::<u32>() as u64 + padding) // payload size + padding
+                .and_then(|size| u32::try_from(size).ok())
+                .ok_or_else(too_large)?;
+            let mut header = BINARY_FRAME_START.to_vec(); // frame start
+            header.extend_from_slice(&frame_size.to_le_bytes()); // frame size
+            header.extend_from_slice(&(data_size as u32).to_le_bytes()); // payload size, fits since the frame size fits
+            header
+        }
+        BinaryVersion::V2 => {
+            let frame_size = data_size
+                .checked_add(size_of::<u64>() as u64 + padding) // payload size + padding
+                .ok_or(std::io::Error::new(
+                    std::io::ErrorKind::OutOfMemory,
+                    "Frame size too large",
+                ))?;
+            let mut header = BINARY_FRAME_START_64.to_vec(); // frame start
+            header.extend_from_slice(&frame_size.to_le_bytes()); // frame size
+            header.extend_from_slice(&data_size.to_le_bytes()); // payload size
+            header
+        }
+    };
+    debug_assert!(
+        header.len() % BINARY_ALIGN as usize == 0,
+        "Frame header of {} bytes is not {BINARY_ALIGN}-byte aligned",
+        header.len()
+    );
+    Ok((header, padding as usize))
+}
+
 #[derive(Debug, Serialize)]
 /// Raw data store marker.
 /// This is used for storing raw data without any specific format.
@@ -161,3 +842,45 @@ impl FmtInfo for Raw {
         Ok(writer)
     }
 }
+
+impl Encoder for Raw {
+    type Frame = [u8];
+
+    fn encode<W: Write>(
+        writer: &mut W,
+        frame: &[u8],
+        _options: &StoreOptions,
+    ) -> std::io::Result<()> {
+        writer.write_all(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_versions() {
+        let data = [1u8, 2, 3, 4, 5];
+        let v1 = store_binary(Vec::new(), &data, BinaryVersion::V1).unwrap();
+        assert_eq!(&v1[..4], b"FRME");
+        assert_eq!(u32::from_le_bytes(v1[4..8].try_into().unwrap()), 12);
+        assert_eq!(u32::from_le_bytes(v1[8..12].try_into().unwrap()), 5);
+        assert_eq!(&v1[12..17], &data);
+        assert_eq!(v1.len(), 20);
+        let v2 = store_binary(Vec::new(), &data, BinaryVersion::V2).unwrap();
+        assert_eq!(&v2[..4], b"FR64");
+        assert_eq!(u64::from_le_bytes(v2[4..12].try_into().unwrap()), 16);
+        assert_eq!(u64::from_le_bytes(v2[12..20].try_into().unwrap()), 5);
+        assert_eq!(&v2[20..25], &data);
+        assert_eq!(v2.len(), 28);
+    }
+
+    #[test]
+    fn test_binary_v1_oversize() {
+        let err = binary_frame_header(u32::MAX as u64, BinaryVersion::V1).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(binary_frame_header(u32::MAX as u64 - 8, BinaryVersion::V1).is_ok());
+        assert!(binary_frame_header(u32::MAX as u64, BinaryVersion::V2).is_ok());
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    ffi::OsString,
+    fs::{remove_file, rename, File},
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    formats::{binary_frame_header, binary_frame_placeholder, binary_padding},
+    index::IndexWriter,
+    BinaryVersion, Codec,
+};
+
+/// Writer for a single data frame file, handed out by the single frame
+/// stores.
+///
+/// Data is written to a hidden temporary file next to the target file.
+/// [FrameWriter::commit] syncs the data to disk, moves the temporary file
+/// into place and registers the frame with the store that handed out the
+/// writer. If the writer is dropped without being committed, the temporary
+/// file is removed and the store is left untouched.
+///
+/// The writer borrows the store mutably, so the store cannot roll over
+/// (and compress) the directory the frame is being written into until the
+/// writer is committed or dropped.
+pub struct FrameWriter<'a> {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    writer: Option<BufWriter<File>>,
+    on_commit: Option<Box<dyn FnOnce() + 'a>>,
+}
+
+impl<'a> FrameWriter<'a> {
+    pub(crate) fn new(
+        path: PathBuf,
+        on_commit: Option<Box<dyn FnOnce() + 'a>>,
+    ) -> Result<Self, std::io::Error> {
+        let tmp_path = partial_path(&path);
+        let writer = File::create(&tmp_path)?;
+        Ok(Self {
+            path,
+            tmp_path,
+            writer: Some(BufWriter::new(writer)),
+            on_commit,
+        })
+    }
+
+    /// Path of the file the frame will be stored in once committed.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Finalize the frame.
+    ///
+    /// The data is flushed and synced to disk, and the temporary file is
+    /// renamed to the target file.
+    ///
+    /// # Returns:
+    /// - `Ok(PathBuf)` with the path of the stored frame.
+    /// - `Err(std::io::Error)` if there was an error during the process. The
+    ///   temporary file is removed in this case.
+    ///
+    /// # Errors:
+    /// - If the target file was created in the meantime, an `std::io::Error` with `AlreadyExists` kind is returned.
+    /// - If there was an error during flushing, syncing or renaming, an `std::io::Error` is returned.
+    /// - If the directory cannot be synced after the rename, an `std::io::Error` is returned.
+    ///   The frame is stored and registered by the store in this case, but the rename may
+    ///   not survive a crash.
+    pub fn commit(mut self) -> Result<PathBuf, std::io::Error> {
+        let writer = self.writer.take().expect("Writer is only taken on commit");
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        drop(file);
+        if self.path.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("File already exists: {:?}", self.path),
+            ));
+        }
+        rename(&self.tmp_path, &self.path)?;
+        // the frame is stored from here on, whether the rename is persisted or not
+        if let Some(on_commit) = self.on_commit.take() {
+            on_commit();
+        }
+        #[cfg(unix)]
+        if let Some(parent) = self.path.parent() {
+            // persist the rename
+            File::open(parent)?.sync_all()?;
+        }
+        Ok(std::mem::take(&mut self.path))
+    }
+}
+
+impl Write for FrameWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer
+            .as_mut()
+            .expect("Writer is only taken on commit")
+            .write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer
+            .as_mut()
+            .expect("Writer is only taken on commit")
+            .flush()
+    }
+}
+
+impl Drop for FrameWriter<'_> {
+    fn drop(&mut self) {
+        // not committed, or the commit failed
+        drop(self.writer.take());
+        if self.tmp_path.exists() {
+            if let Err(e) = remove_file(&self.tmp_path) {
+                log::warn!("Error removing {:?}: {e:?}", self.tmp_path);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Framing {
+    /// Binary frame, sizes are filled in when the frame is finished.
+    /// Compressed payloads are buffered until the frame is finished.
+    Binary(BinaryVersion, Option<Codec>),
+    /// Bytes are written as-is.
+    Raw,
+}
+
+/// Writer for a single data frame of unknown size, handed out by the
+/// appending stores.
+///
+/// The frame is streamed directly into the current data file. For [Binary](crate::Binary)
+/// stores, the frame and payload size fields are filled in by
+/// [FrameStream::finish]. If the stream is dropped without being finished,
+/// the partial frame is removed from the file.
+///
+/// The stream borrows the store mutably, so no other frames can be written
+/// until the stream is finished or dropped.
+pub struct FrameStream<'a> {
+    writer: &'a mut File,
+    start: u64,
+    size: u64,
+    framing: Framing,
+    buffer: Vec<u8>,
+    index: Option<(&'a mut IndexWriter, DateTime<Utc>)>,
+    on_finish: Option<Box<dyn FnOnce() + 'a>>,
+    finished: bool,
+}
+
+impl<'a> FrameStream<'a> {
+    pub(crate) fn new(writer: &'a mut File, framing: Framing) -> Result<Self, std::io::Error> {
+        let start = writer.seek(SeekFrom::End(0))?;
+        let stream = Self {
+            writer,
+            start,
+            size: 0,
+            framing,
+            buffer: Vec::new(),
+            index: None,
+            on_finish: None,
+            finished: false,
+        };
+        if let Framing::Binary(version, _) = framing {
+            stream
+                .writer
+                .write_all(&binary_frame_placeholder(version))?;
+        }
+        Ok(stream)
+    }
+
+    /// Index the frame with timestamp `tstamp` once it is finished.
+    pub(crate) fn indexed(
+        mut self,
+        index: Option<&'a mut IndexWriter>,
+        tstamp: DateTime<Utc>,
+    ) -> Self {
+        self.index = index.map(|index| (index, tstamp));
+        self
+    }
+
+    /// Call `on_finish` once the frame is finished.
+    pub(crate) fn on_finish(mut self, on_finish: Box<dyn FnOnce() + 'a>) -> Self {
+        self.on_finish = Some(on_finish);
+        self
+    }
+
+    /// Number of payload bytes written so far.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Finish the frame.
+    ///
+    /// For binary frames, the payload is compressed if the store uses a [Codec],
+    /// the padding is written and the frame size fields are filled in. The file
+    /// is flushed.
+    ///
+    /// # Errors:
+    /// - If the frame is too large for the data format.
+    /// - If the file cannot be written to or flushed.
+    ///
+    /// The partial frame is removed from the file on error.
+    pub fn finish(mut self) -> Result<(), std::io::Error> {
+        if let Framing::Binary(version, codec) = self.framing {
+            let mut size = self.size;
+            if let Some(codec) = codec {
+                let data = codec.compress(&self.buffer)?;
+                self.writer.write_all(&data)?;
+                size = data.len() as u64;
+            }
+            let (header, padding) = binary_frame_header(size, version)?;
+            self.writer.write_all(binary_padding(padding))?;
+            self.writer.seek(SeekFrom::Start(self.start))?;
+            self.writer.write_all(&header)?;
+            self.writer.seek(SeekFrom::End(0))?;
+        }
+        self.writer.flush()?;
+        if let Some((index, tstamp)) = self.index.take() {
+            let end = self.writer.stream_position()?;
+            index.push(self.start, end - self.start, tstamp)?;
+        }
+        if let Some(on_finish) = self.on_finish.take() {
+            on_finish();
+        }
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl Write for FrameStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = match self.framing {
+            Framing::Binary(_, Some(_)) => {
+                self.buffer.extend_from_slice(buf);
+                buf.len()
+            }
+            _ => self.writer.write(buf)?,
+        };
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for FrameStream<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        // not finished, or finishing failed: remove the partial frame
+        if let Err(e) = self
+            .writer
+            .set_len(self.start)
+            .and_then(|_| self.writer.seek(SeekFrom::Start(self.start)))
+        {
+            log::warn!("Error removing partial frame: {e:?}");
+        }
+    }
+}
+
+/// Hidden temporary file next to `path`: `/dir/.name.partial`.
+pub(crate) fn partial_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".partial");
+    path.with_file_name(name)
+}
@@ -0,0 +1,375 @@
+use std::{
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io::{BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    reader::{decode_payload, read_binary_frame, read_header_frame},
+    FileHeader,
+};
+
+/// Size of an index entry in the sidecar file: offset, length and timestamp.
+const ENTRY_SIZE: usize = 3 * size_of::<u64>();
+/// Timestamp of entries whose timestamp is not known.
+const NO_TIMESTAMP: i64 = i64::MIN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Location of a data frame in a data file.
+pub struct IndexEntry {
+    /// Offset of the start of the frame in the data file.
+    pub offset: u64,
+    /// Length of the frame in the data file, including the frame header and padding.
+    pub len: u64,
+    /// Timestamp the frame was stored with. Frames indexed by rebuilding
+    /// the index from the data file have no timestamp.
+    pub tstamp: Option<DateTime<Utc>>,
+}
+
+impl IndexEntry {
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let tstamp = self
+            .tstamp
+            .and_then(|tstamp| tstamp.timestamp_nanos_opt())
+            .unwrap_or(NO_TIMESTAMP);
+        let mut bytes = [0u8; ENTRY_SIZE];
+        bytes[..8].copy_from_slice(&self.offset.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.len.to_le_bytes());
+        bytes[16..].copy_from_slice(&tstamp.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let tstamp = i64::from_le_bytes(bytes[16..24].try_into().unwrap());
+        Self {
+            offset: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            len: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            tstamp: (tstamp != NO_TIMESTAMP).then(|| DateTime::from_timestamp_nanos(tstamp)),
+        }
+    }
+}
+
+/// Index of the data frames in a binary framed data file, for random access
+/// by frame number or by time.
+///
+/// The index is read from the sidecar file maintained by stores created with
+/// [StoreOptions::index](crate::StoreOptions::index), `/path/to/file.bin.idx`.
+/// Frames missing from the sidecar file, or all frames if there is no sidecar
+/// file, are indexed by scanning the data file.
+///
+/// Usage:
+/// ```rust,no_run
+/// use datastor::FrameIndex;
+/// use chrono::Utc;
+///
+/// let index = FrameIndex::open("test/20250101/20250101000000.bin").unwrap();
+/// if let Some(frame) = index.find(Utc::now()) {
+///     let data = index.read(frame).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameIndex {
+    path: PathBuf,
+    header: FileHeader,
+    entries: Vec<IndexEntry>,
+    /// Timestamps and frame numbers of the frames with a timestamp, sorted by time.
+    by_time: Vec<(DateTime<Utc>, usize)>,
+}
+
+impl FrameIndex {
+    /// Open the index of a data file.
+    ///
+    /// # Arguments:
+    /// - `path`: Path to the data file.
+    ///
+    /// # Returns:
+    /// - `Ok(FrameIndex)` with the index of the frames in the file.
+    /// - `Err(std::io::Error)` if there was an error during the process.
+    ///
+    /// # Errors:
+    /// - If the data file or the sidecar file cannot be opened or read.
+    /// - If the data file does not start with a header, or contains an invalid frame,
+    ///   an `std::io::Error` with `InvalidData` kind is returned.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        Ok(Self::load(path.as_ref())?.0)
+    }
+
+    /// Load the index of a data file, returning the number of entries read from the sidecar file.
+    fn load(path: &Path) -> Result<(Self, usize), std::io::Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let header = read_header_frame(&mut reader)?;
+        let mut entries = match std::fs::read(index_path(path)) {
+            // a partially written entry at the end is ignored
+            Ok(data) => data
+                .chunks_exact(ENTRY_SIZE)
+                .map(IndexEntry::from_bytes)
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let stored = entries.len();
+        // index the frames following the last indexed frame
+        if let Some(last) = entries.last() {
+            reader.seek(SeekFrom::Start(last.offset + last.len))?;
+        }
+        loop {
+            let offset = reader.stream_position()?;
+            match read_binary_frame(&mut reader) {
+                Ok(Some(_)) => entries.push(IndexEntry {
+                    offset,
+                    len: reader.stream_position()? - offset,
+                    tstamp: None,
+                }),
+                // end of the file, or a partially written frame
+                Ok(None) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let mut by_time = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(frame, entry)| entry.tstamp.map(|tstamp| (tstamp, frame)))
+            .collect::<Vec<_>>();
+        by_time.sort();
+        let index = Self {
+            path: path.to_owned(),
+            header,
+            entries,
+            by_time,
+        };
+        Ok((index, stored))
+    }
+
+    /// Header of the data file.
+    pub fn header(&self) -> &FileHeader {
+        &self.header
+    }
+
+    /// Number of frames in the data file.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the data file contains no frames.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index entries of all frames, in the order they are stored in the data file.
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Index entry of frame number `frame`.
+    pub fn get(&self, frame: usize) -> Option<&IndexEntry> {
+        self.entries.get(frame)
+    }
+
+    /// Find the frame with the timestamp nearest to `tstamp`.
+    ///
+    /// Frames without a timestamp are not considered.
+    ///
+    /// # Returns:
+    /// - The number of the frame, or `None` if no frame has a timestamp.
+    pub fn find(&self, tstamp: DateTime<Utc>) -> Option<usize> {
+        let next = self.by_time.partition_point(|(time, _)| *time < tstamp);
+        let after = self.by_time.get(next);
+        let before = next.checked_sub(1).and_then(|prev| self.by_time.get(prev));
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                if tstamp - before.0 <= after.0 - tstamp {
+                    Some(before.1)
+                } else {
+                    Some(after.1)
+                }
+            }
+            (Some((_, frame)), None) | (None, Some((_, frame))) => Some(*frame),
+            (None, None) => None,
+        }
+    }
+
+    /// Read the payload of frame number `frame`, decompressed using the
+    /// [Codec](crate::Codec) recorded in the file header.
+    ///
+    /// # Errors:
+    /// - If there is no such frame, an `std::io::Error` with `NotFound` kind is returned.
+    /// - If the data file cannot be opened or read.
+    /// - If the frame is invalid, or the payload cannot be decompressed, an
+    ///   `std::io::Error` with `InvalidData` kind is returned.
+    pub fn read(&self, frame: usize) -> Result<Vec<u8>, std::io::Error> {
+        let entry = self.get(frame).ok_or(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("No frame {frame} in {:?}", self.path),
+        ))?;
+        let mut reader = File::open(&self.path)?;
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        let payload = read_binary_frame(&mut reader)?
+            .ok_or(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+        decode_payload(&self.header, payload)
+    }
+}
+
+/// Writer for the sidecar index file of a data file, held by the appending stores.
+#[derive(Debug)]
+pub(crate) struct IndexWriter {
+    file: File,
+}
+
+impl IndexWriter {
+    /// Create an empty index for a new data file.
+    pub(crate) fn create(path: &Path) -> Result<Self, std::io::Error> {
+        let file = File::create(index_path(path))?;
+        Ok(Self { file })
+    }
+
+    /// Open the index of an existing data file, indexing the frames
+    /// missing from the sidecar file first.
+    pub(crate) fn open(path: &Path) -> Result<Self, std::io::Error> {
+        let (index, stored) = FrameIndex::load(path)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(index_path(path))?;
+        file.set_len((stored * ENTRY_SIZE) as u64)?;
+        file.seek(SeekFrom::End(0))?;
+        let mut writer = Self { file };
+        writer.write(&index.entries[stored..])?;
+        Ok(writer)
+    }
+
+    /// Index a frame of `len` bytes starting at `offset`.
+    pub(crate) fn push(
+        &mut self,
+        offset: u64,
+        len: u64,
+        tstamp: DateTime<Utc>,
+    ) -> Result<(), std::io::Error> {
+        self.write(&[IndexEntry {
+            offset,
+            len,
+            tstamp: Some(tstamp),
+        }])
+    }
+
+    /// Index consecutive frames ending at the current position of `writer`,
+    /// given their lengths and timestamps.
+    pub(crate) fn append(
+        &mut self,
+        writer: &mut File,
+        frames: &[(u64, DateTime<Utc>)],
+    ) -> Result<(), std::io::Error> {
+        let mut offset = writer.stream_position()? - frames.iter().map(|(len, _)| len).sum::<u64>();
+        let entries = frames
+            .iter()
+            .map(|&(len, tstamp)| {
+                let entry = IndexEntry {
+                    offset,
+                    len,
+                    tstamp: Some(tstamp),
+                };
+                offset += len;
+                entry
+            })
+            .collect::<Vec<_>>();
+        self.write(&entries)
+    }
+
+    fn write(&mut self, entries: &[IndexEntry]) -> Result<(), std::io::Error> {
+        let data = entries
+            .iter()
+            .flat_map(|entry| entry.to_bytes())
+            .collect::<Vec<_>>();
+        self.file.write_all(&data)
+    }
+}
+
+/// Sidecar index file of a data file: `/dir/name.ext.idx`.
+pub(crate) fn index_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.file_name().unwrap_or_default());
+    name.push(".idx");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{IoSlice, Write};
+
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::{Binary, StoreOptions, UtcHourly};
+
+    #[test]
+    fn test_index() {
+        let dir = "test_index";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let options = StoreOptions::new().index(true);
+        let mut store = UtcHourly::<Binary>::new_with_options(
+            dir.into(),
+            false,
+            "testprogram",
+            options.clone(),
+        )
+        .unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let at = |seconds| start + Duration::seconds(seconds);
+        let path = store.store(at(0), &[0]).unwrap();
+        let _ = store
+            .store_vectored(at(10), &[IoSlice::new(&[1]), IoSlice::new(&[1])])
+            .unwrap();
+        let _ = store
+            .store_batch([(at(20), vec![2; 3]), (at(30), vec![3; 4])], false)
+            .unwrap();
+        let mut stream = store.store_stream(at(40)).unwrap();
+        stream.write_all(&[4; 5]).unwrap();
+        stream.finish().unwrap();
+        // discarded streams are not indexed
+        let _ = store.store_stream(at(50)).unwrap();
+
+        let index = FrameIndex::open(&path).unwrap();
+        assert_eq!(index.len(), 5);
+        for (frame, entry) in index.entries().iter().enumerate() {
+            assert_eq!(entry.tstamp, Some(at(10 * frame as i64)));
+            assert_eq!(index.read(frame).unwrap().len(), frame + 1);
+        }
+        assert_eq!(index.find(at(-100)), Some(0));
+        assert_eq!(index.find(at(14)), Some(1));
+        assert_eq!(index.find(at(16)), Some(2));
+        assert_eq!(index.find(at(100)), Some(4));
+        assert!(index.read(5).is_err());
+
+        // rebuilt from the data file, without timestamps
+        drop(store);
+        std::fs::remove_file(index_path(&path)).unwrap();
+        let rebuilt = FrameIndex::open(&path).unwrap();
+        assert_eq!(rebuilt.len(), 5);
+        for (entry, original) in rebuilt.entries().iter().zip(index.entries()) {
+            assert_eq!(
+                (entry.offset, entry.len, entry.tstamp),
+                (original.offset, original.len, None)
+            );
+        }
+        assert_eq!(rebuilt.find(at(0)), None);
+
+        // the sidecar file is rebuilt when the store reopens the file
+        let mut store =
+            UtcHourly::<Binary>::new_with_options(dir.into(), false, "testprogram", options)
+                .unwrap();
+        let _ = store.store(at(60), &[5; 6]).unwrap();
+        let index = FrameIndex::open(&path).unwrap();
+        assert_eq!(index.len(), 6);
+        assert_eq!(index.get(4).unwrap().tstamp, None);
+        assert_eq!(index.find(at(0)), Some(5));
+        assert_eq!(index.read(5).unwrap(), vec![5; 6]);
+        assert_eq!(
+            std::fs::metadata(index_path(&path)).unwrap().len(),
+            6 * ENTRY_SIZE as u64
+        );
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+}
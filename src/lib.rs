@@ -1,15 +1,51 @@
 #![deny(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+mod archive;
+mod batch;
+#[cfg(feature = "parquet")]
+mod columnar;
 mod formats;
+mod framewriter;
+mod index;
 mod lock;
+mod manifest;
+mod options;
+mod reader;
 mod singleframe;
+mod slot;
+mod slotted;
+mod template;
 mod timeboundary;
 mod utcdaily;
 mod utchourly;
 mod utils;
-pub use formats::{Binary, FmtInfo, Json, Raw, BINARY_VERSION};
+pub use batch::BatchSummary;
+#[cfg(feature = "parquet")]
+pub use columnar::Parquet;
+#[cfg(feature = "cbor")]
+pub use formats::Cbor;
+#[cfg(feature = "csv")]
+pub use formats::Csv;
+#[cfg(feature = "msgpack")]
+pub use formats::MsgPack;
+#[cfg(feature = "postcard")]
+pub use formats::Postcard;
+pub use formats::{
+    Binary, BinaryVersion, Codec, Encoder, FileHeader, FmtInfo, Json, Raw, SerdeFmt, TimeSlot,
+    BINARY_VERSION,
+};
+pub use framewriter::{FrameStream, FrameWriter};
+pub use index::{FrameIndex, IndexEntry};
+pub use manifest::{find_runs, Anchor, RunFile, RunManifest};
+pub use options::{OutOfOrder, Run, StoreOptions};
+pub use reader::{read_binary, read_frame, read_frames, read_header, JsonReader};
 pub use singleframe::{ExecCountDailySingleFrame, ExecCountSingleFrame, UtcSingleFrame};
-pub use timeboundary::{ExecCountDaily, ExecCountHourly};
+pub use slot::{
+    CustomSlots, DailySlots, Grouping, HourlySlots, MinutelySlots, SlotLength, SlotPreset,
+};
+pub use slotted::{Slotted, UtcMinutely, UtcSlotted, ZonedDaily, ZonedHourly, ZonedMinutely};
+pub use template::{DiscoveredFile, PathTemplate};
+pub use timeboundary::{ExecCountDaily, ExecCountHourly, ExecCountMinutely, ExecCountSlotted};
 pub use utcdaily::UtcDaily;
 pub use utchourly::UtcHourly;
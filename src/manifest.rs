@@ -0,0 +1,638 @@
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{FmtInfo, StoreOptions, TimeSlot};
+
+/// Default interval between anchors.
+const ANCHOR_INTERVAL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Description of a run of an ExecCount store, such as [ExecCountSlotted](crate::ExecCountSlotted),
+/// stored as `manifest.json` in the run directory.
+///
+/// The manifest is written when the store is created, updated whenever an [Anchor]
+/// is recorded, and when the store is dropped. The end time is only set once the store
+/// has been closed cleanly. A resumed run keeps its start time, anchors and the files of
+/// the earlier stores, as of the last update of the manifest.
+///
+/// The anchors relate the elapsed times of the run to the wall clock, e.g. to find
+/// the files of the runs holding data of a given time range, using [find_runs].
+pub struct RunManifest {
+    /// Wall clock time the run was started.
+    pub started: DateTime<Utc>,
+    /// Wall clock time the store was closed, if it was closed cleanly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended: Option<DateTime<Utc>>,
+    /// Name of the host running the store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Program that is writing the data files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progname: Option<String>,
+    /// Version of this crate.
+    pub version: String,
+    /// Type of the store.
+    pub store: String,
+    /// File format, given by the file extension.
+    pub format: String,
+    /// User metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    /// Total number of data frames stored.
+    #[serde(default)]
+    pub frames: u64,
+    /// Data files of the run, in the order they were created.
+    #[serde(default)]
+    pub files: Vec<RunFile>,
+    /// Anchors of the elapsed time of the run, in the order they were recorded, which
+    /// is also the order of their elapsed times.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anchors: Vec<Anchor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Data file of a run.
+///
+/// The single frame files of [ExecCountSingleFrame](crate::ExecCountSingleFrame) and
+/// [ExecCountDailySingleFrame](crate::ExecCountDailySingleFrame) stores are summarized
+/// per directory instead: `path` is the directory, and `first` and `last` are the
+/// first and last files stored into it.
+pub struct RunFile {
+    /// Path of the file, relative to the run directory. The file is moved into
+    /// the tarball of its directory if the directory has been compressed.
+    ///
+    /// For single frame files, the directory holding the files, which is empty
+    /// for the run directory itself.
+    pub path: PathBuf,
+    /// Number of data frames stored in the file, or single frame files stored in the directory.
+    pub frames: u64,
+    /// First single frame file of the directory, relative to the run directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first: Option<PathBuf>,
+    /// Last single frame file of the directory, relative to the run directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last: Option<PathBuf>,
+    /// Earliest elapsed time of the data frames stored in the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earliest: Option<Duration>,
+    /// Latest elapsed time of the data frames stored in the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<Duration>,
+}
+
+impl RunFile {
+    /// Count a data frame stored with the elapsed time `tdelta`.
+    pub(crate) fn record(&mut self, tdelta: Duration) {
+        self.frames += 1;
+        self.earliest = Some(
+            self.earliest
+                .map_or(tdelta, |earliest| earliest.min(tdelta)),
+        );
+        self.latest = Some(self.latest.map_or(tdelta, |latest| latest.max(tdelta)));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Wall clock time at a given elapsed time of a run.
+pub struct Anchor {
+    /// Time elapsed since the beginning of the run.
+    pub elapsed: Duration,
+    /// Wall clock time at that elapsed time.
+    pub utc: DateTime<Utc>,
+}
+
+impl RunManifest {
+    /// Name of the manifest file in the run directory.
+    pub const FILE_NAME: &'static str = "manifest.json";
+
+    /// Read the manifest of a run.
+    ///
+    /// # Arguments:
+    /// - `run_dir`: Run directory, e.g. `/path/to/root/0000000042`.
+    ///
+    /// # Errors:
+    /// - If the manifest cannot be read.
+    /// - If the manifest cannot be deserialized, an `std::io::Error` with `InvalidData` kind is returned.
+    pub fn read(run_dir: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let reader = BufReader::new(File::open(run_dir.as_ref().join(Self::FILE_NAME))?);
+        serde_json::from_reader(reader)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    /// Start a run in `run_dir`, resuming its manifest if there is one, and write the manifest.
+    pub(crate) fn start<Kind: FmtInfo>(
+        run_dir: &Path,
+        progname: Option<&str>,
+        store: &str,
+        options: &StoreOptions,
+    ) -> Result<Self, std::io::Error> {
+        let manifest = match Self::read(run_dir) {
+            Ok(manifest) => Self {
+                ended: None,
+                anchors: monotonic(manifest.anchors),
+                ..manifest
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self {
+                started: Utc::now(),
+                ended: None,
+                host: hostname(),
+                progname: progname.map(str::to_owned),
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+                store: store.to_owned(),
+                format: Kind::extension().to_owned(),
+                metadata: options.metadata.clone(),
+                frames: 0,
+                files: Vec::new(),
+                anchors: Vec::new(),
+            },
+            Err(e) => return Err(e),
+        };
+        manifest.write(run_dir)?;
+        Ok(manifest)
+    }
+
+    /// Entry of the data file `path` of the run, added if it is not listed yet.
+    ///
+    /// # Returns:
+    /// - The index of the entry in [RunManifest::files].
+    pub(crate) fn file(&mut self, run_dir: &Path, path: &Path) -> usize {
+        let path = path.strip_prefix(run_dir).unwrap_or(path);
+        match self.files.iter().rposition(|file| file.path == path) {
+            Some(index) => index,
+            None => {
+                self.files.push(RunFile {
+                    path: path.to_owned(),
+                    frames: 0,
+                    first: None,
+                    last: None,
+                    earliest: None,
+                    latest: None,
+                });
+                self.files.len() - 1
+            }
+        }
+    }
+
+    /// Record the single frame file `path` of the run, once it has been stored with
+    /// the elapsed time `tdelta`, if any, in the summary of its directory.
+    pub(crate) fn single_frame(&mut self, run_dir: &Path, path: &Path, tdelta: Option<Duration>) {
+        let path = path.strip_prefix(run_dir).unwrap_or(path);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let index = match self
+            .files
+            .iter()
+            .rposition(|file| file.path == dir && file.first.is_some())
+        {
+            Some(index) => index,
+            None => {
+                self.files.push(RunFile {
+                    path: dir.to_owned(),
+                    frames: 0,
+                    first: Some(path.to_owned()),
+                    last: Some(path.to_owned()),
+                    earliest: None,
+                    latest: None,
+                });
+                self.files.len() - 1
+            }
+        };
+        let file = &mut self.files[index];
+        file.first = file.first.take().map(|first| first.min(path.to_owned()));
+        file.last = file.last.take().map(|last| last.max(path.to_owned()));
+        match tdelta {
+            Some(tdelta) => file.record(tdelta),
+            None => file.frames += 1,
+        }
+    }
+
+    /// Record the single frame file handed out to a custom writer, `custom`, along
+    /// with its elapsed time, if any, once the file has been written.
+    pub(crate) fn custom_frame(
+        &mut self,
+        run_dir: &Path,
+        custom: Option<(PathBuf, Option<Duration>)>,
+    ) {
+        if let Some((path, tdelta)) = custom.filter(|(path, _)| path.exists()) {
+            self.single_frame(run_dir, &path, tdelta);
+        }
+    }
+
+    /// Whether an anchor is due at `elapsed`, given the anchor interval of the `options`.
+    pub(crate) fn anchor_due(&self, elapsed: Duration, options: &StoreOptions) -> bool {
+        let interval = options.anchor_interval.unwrap_or(ANCHOR_INTERVAL);
+        self.anchors
+            .iter()
+            .map(|anchor| anchor.elapsed)
+            .max()
+            .is_none_or(|last| elapsed >= last + interval)
+    }
+
+    /// Add an anchor, unless its elapsed time is earlier than the latest anchor.
+    ///
+    /// # Errors:
+    /// - If the elapsed time of the anchor goes backwards, an `std::io::Error` with
+    ///   `InvalidInput` kind is returned.
+    pub(crate) fn push_anchor(&mut self, anchor: Anchor) -> Result<(), std::io::Error> {
+        if let Some(last) = self
+            .anchors
+            .last()
+            .filter(|last| last.elapsed > anchor.elapsed)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Anchor at {:?} is earlier than the latest anchor, at {:?}",
+                    anchor.elapsed, last.elapsed
+                ),
+            ));
+        }
+        self.anchors.push(anchor);
+        Ok(())
+    }
+
+    /// Record an anchor, and write the manifest.
+    ///
+    /// # Errors:
+    /// - If the elapsed time of the anchor goes backwards, see [RunManifest::push_anchor].
+    /// - If the manifest cannot be written.
+    pub(crate) fn anchor(&mut self, run_dir: &Path, anchor: Anchor) -> Result<(), std::io::Error> {
+        self.push_anchor(anchor)?;
+        self.frames = self.files.iter().map(|file| file.frames).sum();
+        self.write(run_dir)
+    }
+
+    /// Elapsed time of the run now, when it is resumed by a new store.
+    ///
+    /// The elapsed time continues from the latest anchor, adding the wall clock time
+    /// since that anchor. It is never earlier than the latest elapsed time of the files.
+    ///
+    /// # Returns:
+    /// - `None` if the run has neither anchors nor elapsed times of files.
+    pub(crate) fn resumed_elapsed(&self) -> Option<Duration> {
+        let anchored = self
+            .anchors
+            .iter()
+            .max_by_key(|anchor| anchor.elapsed)
+            .map(|anchor| {
+                let since = (Utc::now() - anchor.utc).to_std().unwrap_or_default();
+                anchor.elapsed + since
+            });
+        let latest = self.files.iter().filter_map(|file| file.latest).max();
+        anchored.max(latest)
+    }
+
+    /// Wall clock time at the elapsed time `elapsed` of the run.
+    ///
+    /// The time is measured from the latest anchor at or before `elapsed`, or from
+    /// the earliest anchor if there is none.
+    ///
+    /// # Returns:
+    /// - `None` if the run has no anchors, or the time is out of range.
+    pub fn to_utc(&self, elapsed: Duration) -> Option<DateTime<Utc>> {
+        let anchor = self
+            .anchors
+            .iter()
+            .filter(|anchor| anchor.elapsed <= elapsed)
+            .max_by_key(|anchor| anchor.elapsed)
+            .or_else(|| self.anchors.iter().min_by_key(|anchor| anchor.elapsed))?;
+        let delta = match elapsed >= anchor.elapsed {
+            true => TimeDelta::from_std(elapsed - anchor.elapsed).ok()?,
+            false => -TimeDelta::from_std(anchor.elapsed - elapsed).ok()?,
+        };
+        anchor.utc.checked_add_signed(delta)
+    }
+
+    /// Convert a time slot bounded by elapsed times to a time slot bounded by wall clock
+    /// times, e.g. the slot of the [FileHeader](crate::FileHeader) of a file of the run.
+    ///
+    /// # Returns:
+    /// - `None` if the elapsed times cannot be converted, see [RunManifest::to_utc].
+    pub fn slot_to_utc(&self, slot: &TimeSlot) -> Option<TimeSlot> {
+        match slot {
+            TimeSlot::Elapsed { start, end } => Some(TimeSlot::Utc {
+                start: self.to_utc(*start)?,
+                end: self.to_utc(*end)?,
+            }),
+            TimeSlot::Utc { .. } => Some(slot.clone()),
+        }
+    }
+
+    /// Data files of the run holding data frames stored between the wall clock times
+    /// `start` (inclusive) and `end` (exclusive).
+    ///
+    /// Files without elapsed times, such as the files of [ExecCountSingleFrame](crate::ExecCountSingleFrame)
+    /// stores, are not returned.
+    pub fn files_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<&RunFile> {
+        self.files
+            .iter()
+            .filter(|file| {
+                let earliest = file.earliest.and_then(|earliest| self.to_utc(earliest));
+                let latest = file.latest.and_then(|latest| self.to_utc(latest));
+                earliest
+                    .zip(latest)
+                    .is_some_and(|(earliest, latest)| earliest < end && latest >= start)
+            })
+            .collect()
+    }
+
+    /// Close the run, and write the manifest.
+    pub(crate) fn close(&mut self, run_dir: &Path) -> Result<(), std::io::Error> {
+        self.ended = Some(Utc::now());
+        self.frames = self.files.iter().map(|file| file.frames).sum();
+        self.write(run_dir)
+    }
+
+    /// Write the manifest through a temporary file, so that it is never left partially written.
+    fn write(&self, run_dir: &Path) -> Result<(), std::io::Error> {
+        let path = run_dir.join(Self::FILE_NAME);
+        let partial = crate::framewriter::partial_path(&path);
+        let mut writer = File::create(&partial)?;
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        writer.flush()?;
+        writer.sync_all()?;
+        std::fs::rename(partial, path)
+    }
+}
+
+/// Find the runs of an ExecCount store holding data frames stored between the wall clock
+/// times `start` (inclusive) and `end` (exclusive), using the anchors of their manifests.
+///
+/// # Arguments:
+/// - `root_dir`: Root directory of the store, holding its run directories.
+/// - `start`: Start of the time range (inclusive).
+/// - `end`: End of the time range (exclusive).
+///
+/// # Returns:
+/// - `Ok(Vec<(PathBuf, RunManifest)>)` with the run directories and their manifests, in run order.
+///   The matching files of each run are given by [RunManifest::files_between].
+/// - `Err(std::io::Error)` if the root directory cannot be read.
+///
+/// Run directories without a readable manifest are skipped.
+pub fn find_runs(
+    root_dir: impl AsRef<Path>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<(PathBuf, RunManifest)>, std::io::Error> {
+    let mut runs = std::fs::read_dir(root_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let manifest = RunManifest::read(&path)
+                .inspect_err(|e| log::debug!("Skipping {path:?}: {e:?}"))
+                .ok()?;
+            (!manifest.files_between(start, end).is_empty()).then_some((path, manifest))
+        })
+        .collect::<Vec<_>>();
+    runs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(runs)
+}
+
+/// Anchors of a manifest whose elapsed time does not go backwards, in the order they were
+/// recorded. Anchors earlier than a previous anchor, e.g. written by older versions after
+/// resuming a run, are dropped.
+fn monotonic(anchors: Vec<Anchor>) -> Vec<Anchor> {
+    let mut kept: Vec<Anchor> = Vec::with_capacity(anchors.len());
+    for anchor in anchors {
+        match kept.last() {
+            Some(last) if last.elapsed > anchor.elapsed => {
+                log::warn!("Dropping anchor {anchor:?}, earlier than {last:?}");
+            }
+            _ => kept.push(anchor),
+        }
+    }
+    kept
+}
+
+/// Name of the host, if it can be determined.
+fn hostname() -> Option<String> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            rustix::system::uname().nodename().to_str().ok().map(str::to_owned)
+        } else {
+            std::env::var("COMPUTERNAME").ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        Binary, ExecCountDailySingleFrame, ExecCountHourly, ExecCountSingleFrame, Json, Run,
+    };
+
+    #[test]
+    fn test_manifest() {
+        let dir = "test_manifest";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let options = StoreOptions::new().metadata(serde_json::json!({"serial": 42}));
+        let mut store =
+            ExecCountHourly::<Json<u32>>::new_with_options(dir, false, "test", options).unwrap();
+        let run = Path::new(dir).join(format!("{:0>10}", 1));
+        let manifest = RunManifest::read(&run).unwrap();
+        assert_eq!(manifest.store, "ExecCountHourly");
+        assert_eq!(manifest.format, "json");
+        assert_eq!(manifest.progname.as_deref(), Some("test"));
+        assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.metadata, Some(serde_json::json!({"serial": 42})));
+        assert_eq!(manifest.ended, None);
+        store.store(&Duration::from_secs(1), &1).unwrap();
+        store.store(&Duration::from_secs(2), &2).unwrap();
+        store.store(&Duration::from_secs(3600), &3).unwrap();
+        drop(store);
+        let manifest = RunManifest::read(&run).unwrap();
+        assert!(manifest.ended.is_some());
+        assert_eq!(manifest.frames, 3);
+        let secs = |secs: u64| Some(Duration::from_secs(secs));
+        let file = |hour: u32, frames: u64, earliest, latest| RunFile {
+            path: PathBuf::from(format!("{:0>10}/{hour:0>10}.json", 0)),
+            frames,
+            first: None,
+            last: None,
+            earliest,
+            latest,
+        };
+        assert_eq!(
+            manifest.files,
+            vec![
+                file(0, 2, secs(1), secs(2)),
+                file(1, 1, secs(3600), secs(3600))
+            ]
+        );
+
+        // a resumed run keeps its start time and files
+        let options = StoreOptions::new().run(Run::Latest);
+        let mut store =
+            ExecCountHourly::<Json<u32>>::new_with_options(dir, false, "test", options).unwrap();
+        store.store(&Duration::from_secs(3601), &4).unwrap();
+        drop(store);
+        let resumed = RunManifest::read(&run).unwrap();
+        assert_eq!(resumed.started, manifest.started);
+        assert_eq!(resumed.frames, 4);
+        assert_eq!(
+            resumed.files,
+            vec![
+                file(0, 2, secs(1), secs(2)),
+                file(1, 2, secs(3600), secs(3601))
+            ]
+        );
+
+        let dir = Path::new(dir).join("single");
+        let mut store = ExecCountSingleFrame::<Binary>::new(dir.to_str().unwrap()).unwrap();
+        store.store(&[1]).unwrap();
+        store.store(&[2]).unwrap();
+        drop(store);
+        let manifest = RunManifest::read(dir.join(format!("{:0>10}", 1))).unwrap();
+        assert_eq!(manifest.store, "ExecCountSingleFrame");
+        assert_eq!(manifest.progname, None);
+        assert_eq!(manifest.frames, 2);
+        // single frame files are summarized per directory
+        assert_eq!(
+            manifest.files,
+            vec![RunFile {
+                path: PathBuf::new(),
+                frames: 2,
+                first: Some(PathBuf::from(format!("{:0>20}.bin", 1))),
+                last: Some(PathBuf::from(format!("{:0>20}.bin", 2))),
+                earliest: None,
+                latest: None,
+            }]
+        );
+
+        let dir = dir.join("daily");
+        let mut store =
+            ExecCountDailySingleFrame::<Binary>::new(dir.to_str().unwrap(), false).unwrap();
+        store.store(&Duration::from_secs(1), &[1]).unwrap();
+        store.store(&Duration::from_secs(2), &[2]).unwrap();
+        store.store(&Duration::from_secs(86400), &[3]).unwrap();
+        // frames are only recorded once committed, or written by a custom writer
+        drop(
+            store
+                .store_frame_writer(&Duration::from_secs(86401))
+                .unwrap(),
+        );
+        let _ = store
+            .store_custom_writer(&Duration::from_secs(86402))
+            .unwrap();
+        let path = store
+            .store_custom_writer(&Duration::from_secs(86403))
+            .unwrap();
+        std::fs::write(path, [4]).unwrap();
+        drop(store);
+        let manifest = RunManifest::read(dir.join(format!("{:0>10}", 1))).unwrap();
+        let day = |day: u32| PathBuf::from(format!("{day:0>10}"));
+        assert_eq!(manifest.frames, 4);
+        assert_eq!(
+            manifest
+                .files
+                .iter()
+                .map(|file| (&file.path, file.frames, file.earliest, file.latest))
+                .collect::<Vec<_>>(),
+            vec![
+                (&day(0), 2, secs(1), secs(2)),
+                (&day(1), 2, secs(86400), secs(86403))
+            ]
+        );
+        assert_eq!(
+            manifest.files[0].last,
+            Some(day(0).join(format!("{:0>10}.bin", 2)))
+        );
+        std::fs::remove_dir_all("test_manifest").unwrap_or_default();
+    }
+
+    #[test]
+    fn test_anchors() {
+        use chrono::TimeZone;
+
+        let dir = "test_anchors";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let hours = |hours: f64| Duration::from_secs_f64(hours * 3600.0);
+        let mut store = ExecCountHourly::<Json<u32>>::new(dir, false, "test").unwrap();
+        let anchor = store.anchor().unwrap();
+        assert!(anchor.elapsed < hours(1.0));
+        let utc = Utc.with_ymd_and_hms(2025, 1, 1, 1, 0, 0).unwrap();
+        store
+            .record_anchor(Anchor {
+                elapsed: hours(1.0),
+                utc,
+            })
+            .unwrap();
+        // anchors going backwards are rejected
+        let err = store
+            .record_anchor(Anchor {
+                elapsed: hours(0.5),
+                utc,
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        store.store(&hours(1.5), &1).unwrap();
+        store.store(&hours(2.25), &2).unwrap();
+        drop(store);
+        let run = Path::new(dir).join(format!("{:0>10}", 1));
+        let manifest = RunManifest::read(&run).unwrap();
+        // created, on demand and recorded, the elapsed time of the store when it
+        // is dropped being earlier than the recorded anchor
+        assert_eq!(manifest.anchors.len(), 3);
+        assert_eq!(
+            manifest.anchors[2],
+            Anchor {
+                elapsed: hours(1.0),
+                utc
+            }
+        );
+        assert_eq!(
+            manifest.to_utc(hours(1.5)),
+            Some(utc + TimeDelta::minutes(30))
+        );
+        let slot = TimeSlot::Elapsed {
+            start: hours(2.0),
+            end: hours(3.0),
+        };
+        assert_eq!(
+            manifest.slot_to_utc(&slot),
+            Some(TimeSlot::Utc {
+                start: utc + TimeDelta::hours(1),
+                end: utc + TimeDelta::hours(2),
+            })
+        );
+        let files = manifest.files_between(utc, utc + TimeDelta::hours(1));
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].earliest, Some(hours(1.5)));
+
+        let runs = find_runs(dir, utc + TimeDelta::hours(1), utc + TimeDelta::hours(2)).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].0, run);
+        let runs = find_runs(dir, utc + TimeDelta::hours(2), utc + TimeDelta::hours(3)).unwrap();
+        assert!(runs.is_empty());
+
+        // a resumed run continues after the latest anchor, dropping earlier ones
+        let mut manifest = RunManifest::read(&run).unwrap();
+        manifest.anchors.push(Anchor {
+            elapsed: hours(0.5),
+            utc,
+        });
+        manifest.write(&run).unwrap();
+        let options = StoreOptions::new().run(Run::Latest);
+        let store =
+            ExecCountHourly::<Json<u32>>::new_with_options(dir, false, "test", options).unwrap();
+        assert!(store.elapsed() >= hours(1.0));
+        drop(store);
+        let anchors = RunManifest::read(&run).unwrap().anchors;
+        assert_eq!(anchors.len(), 5);
+        assert!(anchors
+            .windows(2)
+            .all(|pair| pair[0].elapsed <= pair[1].elapsed));
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+}
@@ -0,0 +1,218 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use chrono::TimeDelta;
+
+use crate::{BinaryVersion, Codec, FileHeader, FmtInfo, PathTemplate, TimeSlot};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Handling of data frames older than the time slot of the latest frame stored.
+pub enum OutOfOrder {
+    /// Store the frame in the file of its own time slot, reopening the file,
+    /// or restoring its directory from its tarball if it has been compressed.
+    #[default]
+    Backfill,
+    /// Refuse the frame with an `std::io::Error` of `InvalidInput` kind.
+    Reject,
+    /// Store the frame in the file of the latest time slot.
+    Clamp,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Run directory used by ExecCount stores, such as [ExecCountSlotted](crate::ExecCountSlotted).
+pub enum Run {
+    /// Start a new run, numbered after the latest run in the root directory.
+    #[default]
+    Next,
+    /// Resume the latest run in the root directory, or start the first run if there is none.
+    Latest,
+    /// Resume the run with the given number, or start it if it does not exist.
+    Id(u32),
+}
+
+#[derive(Debug, Clone, Default)]
+/// Options for a data store.
+///
+/// The options are passed to the `new_with_options` constructor of a store,
+/// and apply to all files created by that store.
+///
+/// Usage:
+/// ```rust,no_run
+/// use datastor::{UtcHourly, Binary, BinaryVersion, StoreOptions};
+/// let options = StoreOptions::new().binary_version(BinaryVersion::V2);
+/// let mut store =
+///     UtcHourly::<Binary>::new_with_options("test".into(), true, "testprogram", options).unwrap();
+/// ```
+pub struct StoreOptions {
+    pub(crate) binary_version: BinaryVersion,
+    pub(crate) codec: Option<Codec>,
+    pub(crate) index: bool,
+    pub(crate) template: Option<PathTemplate>,
+    pub(crate) out_of_order: OutOfOrder,
+    pub(crate) run: Run,
+    pub(crate) anchor_interval: Option<std::time::Duration>,
+    pub(crate) grace_period: TimeDelta,
+    pub(crate) grace_directories: usize,
+    pub(crate) schema: Option<String>,
+    pub(crate) schema_version: Option<u32>,
+    pub(crate) metadata: Option<serde_json::Value>,
+    pub(crate) row_group_size: Option<usize>,
+}
+
+impl StoreOptions {
+    /// Create the default store options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the binary frame format version used by [Binary](crate::Binary) stores.
+    /// Defaults to [BinaryVersion::V1].
+    pub fn binary_version(mut self, version: BinaryVersion) -> Self {
+        self.binary_version = version;
+        self
+    }
+
+    /// Set the codec used to compress the payload of each binary frame, in files
+    /// of [Binary](crate::Binary) and other binary framed formats. The codec is
+    /// recorded in the header of every new file. Files of single frame stores are
+    /// not compressed. Defaults to no compression.
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    /// Set whether appending stores of [Binary](crate::Binary) and other binary
+    /// framed formats maintain a sidecar index file next to each data file,
+    /// recording the offset, length and timestamp of every frame. The index is
+    /// read using [FrameIndex](crate::FrameIndex). Defaults to no index.
+    pub fn index(mut self, index: bool) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Set the template of the paths of the files of [Slotted](crate::Slotted) stores,
+    /// such as [UtcHourly](crate::UtcHourly) and [UtcDaily](crate::UtcDaily),
+    /// replacing their default layout and [Grouping](crate::Grouping).
+    /// Other stores ignore the template.
+    pub fn path_template(mut self, template: PathTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Set the handling of data frames older than the time slot of the latest
+    /// frame stored by [Slotted](crate::Slotted) and [UtcSingleFrame](crate::UtcSingleFrame)
+    /// stores. Defaults to [OutOfOrder::Backfill].
+    pub fn out_of_order(mut self, policy: OutOfOrder) -> Self {
+        self.out_of_order = policy;
+        self
+    }
+
+    /// Set the run directory used by ExecCount stores. Defaults to [Run::Next].
+    ///
+    /// When a run is resumed, the store keeps appending to its latest files, and the
+    /// elapsed time measured by the store continues from the latest [anchor](crate::Anchor)
+    /// of the [RunManifest](crate::RunManifest), counting the wall clock time since that
+    /// anchor. It is never earlier than the latest elapsed time of the files of the run.
+    /// Runs without a manifest count the time since their latest file was last modified.
+    pub fn run(mut self, run: Run) -> Self {
+        self.run = run;
+        self
+    }
+
+    /// Set the interval between the [anchors](crate::Anchor) recorded in the
+    /// [RunManifest](crate::RunManifest) of ExecCount stores, measured by the elapsed
+    /// time of the store. Anchors are recorded when frames are stored. Defaults to an hour.
+    pub fn anchor_interval(mut self, interval: std::time::Duration) -> Self {
+        self.anchor_interval = Some(interval);
+        self
+    }
+
+    /// Set how long a directory is kept uncompressed after the end of the last
+    /// time slot written into it, measured by the timestamps of the frames stored.
+    /// Late frames within the grace period are written without restoring the
+    /// directory from its tarball. Defaults to no grace period.
+    ///
+    /// Directories left uncompressed when a store is dropped are compressed by
+    /// the next store using the same root directory, once their grace period has passed.
+    pub fn grace_period(mut self, grace: TimeDelta) -> Self {
+        self.grace_period = grace;
+        self
+    }
+
+    /// Set the number of directories with later data that must exist before a
+    /// directory is compressed, e.g. 2 to keep the daily directory of a store
+    /// uncompressed until the second day after it starts. Applies in addition to the
+    /// [grace period](StoreOptions::grace_period). Defaults to none.
+    pub fn grace_directories(mut self, count: usize) -> Self {
+        self.grace_directories = count;
+        self
+    }
+
+    /// Set the identifier of the schema of the stored data frames,
+    /// recorded in the header of every new file. [Json](crate::Json) stores
+    /// default to the name of the stored type.
+    ///
+    /// The schema is part of the identity of the lock held by the store.
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_owned());
+        self
+    }
+
+    /// Set the version of the schema of the stored data frames,
+    /// recorded in the header of every new file.
+    ///
+    /// The schema version is part of the identity of the lock held by the store.
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
+    /// Set user metadata, such as instrument serial numbers or calibration
+    /// identifiers, embedded in the header of every new file.
+    pub fn metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Set the number of data frames collected into each row group by
+    /// columnar stores. Defaults to 10000.
+    pub fn row_group_size(mut self, rows: usize) -> Self {
+        self.row_group_size = Some(rows.max(1));
+        self
+    }
+
+    /// Header information for a new file created by `progname` using a `store`.
+    pub(crate) fn file_header<Kind: FmtInfo>(
+        &self,
+        progname: &str,
+        store: &str,
+        slot: TimeSlot,
+    ) -> FileHeader {
+        FileHeader {
+            version: self.binary_version,
+            slot: Some(slot),
+            codec: self.codec.filter(|_| Kind::framed()),
+            schema: self.schema_name::<Kind>(),
+            schema_version: self.schema_version,
+            metadata: self.metadata.clone(),
+            ..FileHeader::new::<Kind>(progname, store)
+        }
+    }
+
+    /// Schema name recorded for files of format `Kind`.
+    pub(crate) fn schema_name<Kind: FmtInfo>(&self) -> Option<String> {
+        self.schema.clone().or_else(Kind::schema_name)
+    }
+
+    /// Hash identifying the lock held by a store of format `Kind`.
+    pub(crate) fn lock_hash<Kind: FmtInfo>(&self) -> u64 {
+        let schema = self.schema_name::<Kind>();
+        if schema.is_none() && self.schema_version.is_none() {
+            return Kind::type_hash();
+        }
+        let mut hasher = DefaultHasher::new();
+        Kind::type_hash().hash(&mut hasher);
+        schema.hash(&mut hasher);
+        self.schema_version.hash(&mut hasher);
+        hasher.finish()
+    }
+}
@@ -0,0 +1,553 @@
+use serde::de::DeserializeOwned;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    marker::PhantomData,
+    path::Path,
+};
+
+use crate::{formats::JsonHeader, FileHeader, SerdeFmt};
+
+type Upcaster = Box<dyn Fn(serde_json::Value) -> serde_json::Value>;
+
+/// Read the header of a [Binary](crate::Binary) or [Json](crate::Json) data file.
+///
+/// # Arguments:
+/// - `path`: Path to the data file.
+///
+/// # Returns:
+/// - `Ok(FileHeader)` with the header of the file.
+/// - `Err(std::io::Error)` if there was an error during the process.
+///
+/// # Errors:
+/// - If the file cannot be opened or read.
+/// - If the file does not start with a header, e.g. [Raw](crate::Raw) files or binary files
+///   written by earlier versions of this crate, an `std::io::Error` with `InvalidData` kind is returned.
+///   JSONL files written by earlier versions have a header without schema version.
+///
+/// # Example:
+/// ```rust,no_run
+/// use datastor::read_header;
+///
+/// let header = read_header("test/20250101/20250101000000.bin").unwrap();
+/// println!("Created by {} at {}", header.progname, header.created);
+/// ```
+pub fn read_header<P: AsRef<Path>>(path: P) -> Result<FileHeader, std::io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(b"{") {
+        // JSONL header line
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        JsonHeader::parse(&line)
+    } else {
+        // binary header frame
+        let frame = read_binary_frame(&mut reader)?.ok_or(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "File is empty",
+        ))?;
+        serde_json::from_slice(&frame)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Read all data frames of a [Binary](crate::Binary) file created by an appending store.
+///
+/// Payloads compressed with the [Codec](crate::Codec) recorded in the header are decompressed.
+///
+/// # Arguments:
+/// - `path`: Path to the data file.
+///
+/// # Returns:
+/// - `Ok((FileHeader, Vec<Vec<u8>>))` with the header and the payloads of the data frames.
+/// - `Err(std::io::Error)` if there was an error during the process.
+///
+/// # Errors:
+/// - If the file cannot be opened or read.
+/// - If the header cannot be deserialized, or a payload cannot be decompressed,
+///   an `std::io::Error` with `InvalidData` kind is returned.
+pub fn read_binary(path: impl AsRef<Path>) -> Result<(FileHeader, Vec<Vec<u8>>), std::io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let header = read_header_frame(&mut reader)?;
+    let mut frames = Vec::new();
+    while let Some(frame) = read_binary_frame(&mut reader)? {
+        frames.push(decode_payload(&header, frame)?);
+    }
+    Ok((header, frames))
+}
+
+/// Read all data frames of a file created by an appending store of a [SerdeFmt] format.
+///
+/// # Arguments:
+/// - `path`: Path to the data file.
+///
+/// # Returns:
+/// - `Ok((FileHeader, Vec<Kind::Frame>))` with the header and the data frames of the file.
+/// - `Err(std::io::Error)` if there was an error during the process.
+///
+/// # Errors:
+/// - If the file cannot be opened or read.
+/// - If the header or a data frame cannot be deserialized, an `std::io::Error` with `InvalidData` kind is returned.
+pub fn read_frames<Kind: SerdeFmt>(
+    path: impl AsRef<Path>,
+) -> Result<(FileHeader, Vec<Kind::Frame>), std::io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let header = read_header_frame(&mut reader)?;
+    let mut frames = Vec::new();
+    while let Some(frame) = read_binary_frame(&mut reader)? {
+        frames.push(Kind::from_bytes(&decode_payload(&header, frame)?)?);
+    }
+    Ok((header, frames))
+}
+
+/// Read the header frame at the start of a binary framed file.
+pub(crate) fn read_header_frame<R: Read>(reader: &mut R) -> Result<FileHeader, std::io::Error> {
+    let header = read_binary_frame(reader)?.ok_or(std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        "File is empty",
+    ))?;
+    serde_json::from_slice(&header)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+/// Decompress a frame payload using the codec recorded in the file header.
+pub(crate) fn decode_payload(
+    header: &FileHeader,
+    payload: Vec<u8>,
+) -> Result<Vec<u8>, std::io::Error> {
+    match header.codec {
+        Some(codec) => codec.decompress(&payload),
+        None => Ok(payload),
+    }
+}
+
+/// Read the data frame of a file created by a single frame store of a [SerdeFmt] format.
+///
+/// # Errors:
+/// - If the file cannot be opened or read.
+/// - If the data frame cannot be deserialized, an `std::io::Error` with `InvalidData` kind is returned.
+pub fn read_frame<Kind: SerdeFmt>(path: impl AsRef<Path>) -> Result<Kind::Frame, std::io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let frame = read_binary_frame(&mut reader)?.ok_or(std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        "File is empty",
+    ))?;
+    Kind::from_bytes(&frame)
+}
+
+/// Reader for [Json](crate::Json) data files.
+///
+/// Files record the version of the schema they were written with
+/// (see [StoreOptions::schema_version](crate::StoreOptions::schema_version)),
+/// files without a schema version are treated as version 0. Records written
+/// with an older schema version are upgraded one version at a time using the
+/// registered upcasters, before being deserialized into `T`.
+///
+/// # Example:
+/// ```rust,no_run
+/// use datastor::JsonReader;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Reading {
+///     value: f64,
+///     unit: String,
+/// }
+///
+/// let mut reader = JsonReader::<Reading>::new(1);
+/// // version 0 records did not have a unit
+/// reader.register_upcaster(0, |mut record| {
+///     record["unit"] = "V".into();
+///     record
+/// });
+/// let (header, records) = reader.read("test/20250101/20250101000000.json").unwrap();
+/// ```
+pub struct JsonReader<T> {
+    version: u32,
+    upcasters: BTreeMap<u32, Upcaster>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> JsonReader<T> {
+    /// Create a reader for records of schema `version`.
+    pub fn new(version: u32) -> Self {
+        Self {
+            version,
+            upcasters: BTreeMap::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Register an upcaster converting a record of schema version `from`
+    /// into a record of schema version `from + 1`.
+    pub fn register_upcaster<F>(&mut self, from: u32, upcaster: F) -> &mut Self
+    where
+        F: Fn(serde_json::Value) -> serde_json::Value + 'static,
+    {
+        self.upcasters.insert(from, Box::new(upcaster));
+        self
+    }
+
+    /// Read all records of a data file.
+    ///
+    /// # Arguments:
+    /// - `path`: Path to the data file.
+    ///
+    /// # Returns:
+    /// - `Ok((FileHeader, Vec<T>))` with the header and the records of the file.
+    /// - `Err(std::io::Error)` if there was an error during the process.
+    ///
+    /// # Errors:
+    /// - If the file cannot be opened or read.
+    /// - If the file was written with a newer schema version, or an upcaster is missing,
+    ///   an `std::io::Error` with `InvalidData` kind is returned.
+    /// - If a record cannot be deserialized, an `std::io::Error` with `InvalidData` kind is returned.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<(FileHeader, Vec<T>), std::io::Error> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = lines.next().ok_or(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "File is empty",
+        ))??;
+        let header = JsonHeader::parse(&header)?;
+        let version = header.schema_version.unwrap_or(0);
+        if version > self.version {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "File schema version {version} is newer than {}",
+                    self.version
+                ),
+            ));
+        }
+        let upcasters = (version..self.version)
+            .map(|from| {
+                self.upcasters.get(&from).ok_or(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("No upcaster from schema version {from}"),
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut records = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            let record = upcasters
+                .iter()
+                .fold(record, |record, upcaster| upcaster(record));
+            records.push(
+                T::deserialize(record)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?,
+            );
+        }
+        Ok((header, records))
+    }
+}
+
+/// Read the payload of the next binary frame.
+///
+/// Returns `Ok(None)` at the end of the data.
+pub(crate) fn read_binary_frame<R: Read>(
+    reader: &mut R,
+) -> Result<Option<Vec<u8>>, std::io::Error> {
+    let mut magic = [0u8; 4];
+    if !read_exact_or_eof(reader, &mut magic)? {
+        return Ok(None);
+    }
+    let (frame_size, data_size) = match &magic {
+        b"FRME" => {
+            let mut sizes = [0u8; 8];
+            reader.read_exact(&mut sizes)?;
+            let frame_size = u32::from_le_bytes(sizes[..4].try_into().unwrap()) as u64;
+            let data_size = u32::from_le_bytes(sizes[4..].try_into().unwrap()) as u64;
+            (frame_size.checked_sub(4), data_size)
+        }
+        b"FR64" => {
+            let mut sizes = [0u8; 16];
+            reader.read_exact(&mut sizes)?;
+            let frame_size = u64::from_le_bytes(sizes[..8].try_into().unwrap());
+            let data_size = u64::from_le_bytes(sizes[8..].try_into().unwrap());
+            (frame_size.checked_sub(8), data_size)
+        }
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid frame start: {magic:?}"),
+            ))
+        }
+    };
+    // the rest of the frame must hold the payload
+    let padding = frame_size
+        .and_then(|size| size.checked_sub(data_size))
+        .ok_or(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Payload size exceeds frame size",
+        ))?;
+    let mut data = Vec::new();
+    reader.take(data_size).read_to_end(&mut data)?;
+    if (data.len() as u64) < data_size {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    std::io::copy(&mut reader.take(padding), &mut std::io::sink())?;
+    Ok(Some(data))
+}
+
+/// Fill `buf` completely. Returns `Ok(false)` if no data is left.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, std::io::Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    match filled {
+        0 => Ok(false),
+        filled if filled < buf.len() => Err(std::io::ErrorKind::UnexpectedEof.into()),
+        _ => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, Utc};
+
+    use super::*;
+    use crate::{Binary, BinaryVersion, Json, StoreOptions, TimeSlot, UtcHourly};
+
+    #[test]
+    fn test_read_header() {
+        let dir = "test_read_header";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let tstamp = DateTime::parse_from_rfc3339("2025-01-01T12:34:56Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let slot = TimeSlot::Utc {
+            start: tstamp - Duration::seconds(34 * 60 + 56),
+            end: tstamp + Duration::seconds(25 * 60 + 4),
+        };
+        let options = StoreOptions::new()
+            .binary_version(BinaryVersion::V2)
+            .schema("camera/v1");
+        let mut store =
+            UtcHourly::<Binary>::new_with_options(dir.into(), false, "testprogram", options)
+                .unwrap();
+        let path = store.store(tstamp, &[1, 2, 3]).unwrap();
+        let header = read_header(&path).unwrap();
+        assert_eq!(header.version, BinaryVersion::V2);
+        assert_eq!(header.format, "bin");
+        assert_eq!(header.progname, "testprogram");
+        assert_eq!(header.store, "UtcHourly");
+        assert_eq!(header.slot, Some(slot.clone()));
+        assert_eq!(header.schema.as_deref(), Some("camera/v1"));
+        // the data frame follows the header frame
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        read_binary_frame(&mut reader).unwrap().unwrap();
+        assert_eq!(read_binary_frame(&mut reader).unwrap().unwrap(), [1, 2, 3]);
+        assert!(read_binary_frame(&mut reader).unwrap().is_none());
+
+        let mut store = UtcHourly::<Json<u32>>::new(dir.into(), false, "testprogram").unwrap();
+        let path = store.store(tstamp, &42).unwrap();
+        let header = read_header(&path).unwrap();
+        assert_eq!(header.version, BinaryVersion::V1);
+        assert_eq!(header.format, "json");
+        assert_eq!(header.slot, Some(slot));
+        assert_eq!(header.schema.as_deref(), Some("u32"));
+        assert_eq!(header.metadata, None);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_read_metadata() {
+        let dir = "test_read_metadata";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let metadata = serde_json::json!({
+            "serial": "SN-0042",
+            "firmware": "1.2.3",
+            "calibration": ["CAL-7", "CAL-9"],
+        });
+        let options = StoreOptions::new().metadata(metadata.clone());
+        let mut store = crate::ExecCountDaily::<Binary>::new_with_options(
+            dir,
+            false,
+            "testprogram",
+            options.clone(),
+        )
+        .unwrap();
+        store
+            .store(&std::time::Duration::from_secs(1), &[1])
+            .unwrap();
+        let path = std::path::Path::new(dir).join(format!("{:0>10}/{:0>10}/{:0>10}.bin", 1, 0, 0));
+        let header = read_header(path).unwrap();
+        assert_eq!(header.store, "ExecCountDaily");
+        assert_eq!(header.metadata.as_ref(), Some(&metadata));
+
+        let mut store =
+            UtcHourly::<Json<u32>>::new_with_options(dir.into(), false, "testprogram", options)
+                .unwrap();
+        let path = store.store(Utc::now(), &42).unwrap();
+        let header = read_header(path).unwrap();
+        assert_eq!(header.metadata, Some(metadata));
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_json_upcast() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize)]
+        struct ReadingV0 {
+            value: f64,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Reading {
+            value: f64,
+            unit: String,
+        }
+
+        let dir = "test_json_upcast";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let tstamp = Utc::now();
+        let options = StoreOptions::new().schema("reading");
+        let mut store =
+            UtcHourly::<Json<ReadingV0>>::new_with_options(dir.into(), false, "old", options)
+                .unwrap();
+        let old = store.store(tstamp, &ReadingV0 { value: 1.5 }).unwrap();
+        drop(store);
+        // a different schema version takes a different lock, but cannot append to old files
+        let options = StoreOptions::new().schema("reading").schema_version(1);
+        let mut store =
+            UtcHourly::<Json<Reading>>::new_with_options(dir.into(), false, "new", options.clone())
+                .unwrap();
+        assert!(
+            UtcHourly::<Json<Reading>>::new_with_options(dir.into(), false, "new", options)
+                .is_err()
+        );
+        let reading = Reading {
+            value: 2.5,
+            unit: "mV".into(),
+        };
+        let err = store.store(tstamp, &reading).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let new = store.store(tstamp + Duration::hours(1), &reading).unwrap();
+        let header = read_header(&new).unwrap();
+        assert_eq!(header.schema.as_deref(), Some("reading"));
+        assert_eq!(header.schema_version, Some(1));
+
+        let mut reader = JsonReader::<Reading>::new(1);
+        assert!(reader.read(&old).is_err()); // no upcaster
+        reader.register_upcaster(0, |mut record| {
+            record["unit"] = "V".into();
+            record
+        });
+        let (_, records) = reader.read(&old).unwrap();
+        assert_eq!(
+            records,
+            vec![Reading {
+                value: 1.5,
+                unit: "V".into()
+            }]
+        );
+        let (_, records) = reader.read(&new).unwrap();
+        assert_eq!(records, vec![reading]);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_json_baseline() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Reading {
+            value: f64,
+            unit: String,
+        }
+
+        let dir = "test_json_baseline";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        std::fs::create_dir_all(dir).unwrap();
+        // written by earlier versions, without header fields
+        let path = std::path::Path::new(dir).join("20250101000000.json");
+        std::fs::write(
+            &path,
+            "{\"header\":\"This file is created by old.\"}\n{\"value\":1.5}\n",
+        )
+        .unwrap();
+        let header = read_header(&path).unwrap();
+        assert_eq!(header.progname, "old");
+        assert_eq!(header.schema_version, None);
+        let mut reader = JsonReader::<Reading>::new(1);
+        reader.register_upcaster(0, |mut record| {
+            record["unit"] = "V".into();
+            record
+        });
+        let (_, records) = reader.read(&path).unwrap();
+        assert_eq!(
+            records,
+            vec![Reading {
+                value: 1.5,
+                unit: "V".into()
+            }]
+        );
+        // other objects are not headers
+        std::fs::write(&path, "{\"value\":1.5}\n").unwrap();
+        let err = read_header(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[cfg(any(feature = "msgpack", feature = "cbor", feature = "postcard"))]
+    fn serde_roundtrip<Kind>(dir: &str)
+    where
+        Kind: SerdeFmt<Frame = Vec<(String, u32)>> + crate::Encoder<Frame = Vec<(String, u32)>>,
+    {
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let tstamp = DateTime::parse_from_rfc3339("2025-01-01T12:34:56Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let frames = (0..3)
+            .map(|idx| vec![("frame".to_owned(), idx), ("size".to_owned(), 42)])
+            .collect::<Vec<_>>();
+        let mut store = UtcHourly::<Kind>::new(dir.into(), false, "testprogram").unwrap();
+        let path = store.store(tstamp, &frames[0]).unwrap();
+        let summary = store
+            .store_batch(frames[1..].iter().map(|frame| (tstamp, frame)), false)
+            .unwrap();
+        assert_eq!(summary.files, vec![(path.clone(), 2)]);
+        let (header, stored) = read_frames::<Kind>(&path).unwrap();
+        assert_eq!(header.format, Kind::extension());
+        assert_eq!(path.extension().unwrap(), Kind::extension());
+        assert_eq!(stored, frames);
+
+        let mut store = crate::ExecCountSingleFrame::<Kind>::new(&format!("{dir}/single")).unwrap();
+        store.store(&frames[0]).unwrap();
+        let path = format!(
+            "{dir}/single/0000000001/00000000000000000001.{}",
+            Kind::extension()
+        );
+        assert_eq!(read_frame::<Kind>(path).unwrap(), frames[0]);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack() {
+        serde_roundtrip::<crate::MsgPack<_>>("test_msgpack");
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor() {
+        serde_roundtrip::<crate::Cbor<_>>("test_cbor");
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn test_postcard() {
+        serde_roundtrip::<crate::Postcard<_>>("test_postcard");
+    }
+}
@@ -1,6 +1,5 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
 use lazy_static::lazy_static;
-use serde::Serialize;
 use std::{
     ffi::OsStr,
     fs::File,
@@ -9,13 +8,13 @@ use std::{
     path::PathBuf,
     sync::{mpsc, Arc, Mutex},
     thread::JoinHandle,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    formats::store_binary,
-    utils::{find_max_iter, get_compressor, UtcDailyBoundary},
-    Binary, FmtInfo, Json,
+    archive::ArchiveQueue,
+    utils::{find_last, get_compressor, resumed_elapsed, run_dir, UtcDailyBoundary},
+    Anchor, Encoder, FmtInfo, FrameWriter, OutOfOrder, RunManifest, StoreOptions,
 };
 
 #[derive(Debug)]
@@ -28,7 +27,12 @@ use crate::{
 ///
 /// If compression is enabled, the files for each day are compressed into a tarball
 /// at the end of the day, as /path/to/root/YYYYMMDD.tar.gz. The directory YYYYMMDD
-/// is removed after successful compression.
+/// is removed after successful compression. Compression is deferred by the grace
+/// period of the store ([StoreOptions::grace_period]).
+///
+/// Data frames older than the day of the latest frame stored are handled according
+/// to [StoreOptions::out_of_order]. [OutOfOrder::Clamp] stores them in the directory
+/// of the latest day, keeping their own timestamp in the file name.
 ///
 /// Usage:
 /// ```rust,no_run
@@ -48,8 +52,11 @@ pub struct UtcSingleFrame<Kind> {
     root_dir: PathBuf,
     current_dir: PathBuf,
     last_date: Option<String>,
+    latest: Option<DateTime<Utc>>,
     compress_tx: Option<mpsc::Sender<Option<PathBuf>>>,
     compress_hdl: Option<JoinHandle<()>>,
+    archive: ArchiveQueue,
+    options: StoreOptions,
     _marker: PhantomData<Kind>,
 }
 
@@ -72,6 +79,21 @@ impl<Kind> UtcSingleFrame<Kind> {
     /// - `root_dir`: Root directory where data files will be stored.
     /// - `compress`: Whether individual, hourly files will be compressed into a tarball at the end of the day.
     pub fn new(root_dir: PathBuf, compress: bool) -> Result<Self, std::io::Error> {
+        Self::new_with_options(root_dir, compress, StoreOptions::default())
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether individual, hourly files will be compressed into a tarball at the end of the day.
+    /// - `options`: Options applied to the files created by this store.
+    pub fn new_with_options(
+        root_dir: PathBuf,
+        compress: bool,
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
         std::fs::create_dir_all(&root_dir)?;
         lazy_static! {
             static ref COMPRESSION_THREAD_TX: Arc<Mutex<Option<mpsc::Sender<Option<PathBuf>>>>> =
@@ -80,12 +102,32 @@ impl<Kind> UtcSingleFrame<Kind> {
         // handle compression
         let (compress_tx, compress_hdl) =
             get_compressor(compress, (*COMPRESSION_THREAD_TX).clone());
+        let mut archive = ArchiveQueue::new(compress_tx.clone(), &options);
+        if archive.enabled() {
+            // directories left uncompressed by earlier executions
+            for entry in std::fs::read_dir(&root_dir)? {
+                let path = entry?.path();
+                let date = path
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .and_then(|name| NaiveDate::parse_from_str(name, "%Y%m%d").ok());
+                if let (true, Some(date)) = (path.is_dir(), date) {
+                    let end = (date + TimeDelta::days(1))
+                        .and_time(NaiveTime::MIN)
+                        .and_utc();
+                    archive.push(path, end);
+                }
+            }
+        }
         Ok(Self {
             root_dir,
             current_dir: PathBuf::new(),
             last_date: None,
+            latest: None,
+            archive,
             compress_tx,
             compress_hdl,
+            options,
             _marker: PhantomData,
         })
     }
@@ -112,8 +154,20 @@ impl<Kind: FmtInfo> UtcDailyBoundary for UtcSingleFrame<Kind> {
         &self.root_dir
     }
 
-    fn get_compressor(&mut self) -> Option<mpsc::Sender<Option<PathBuf>>> {
-        self.compress_tx.clone()
+    fn get_archive(&mut self) -> &mut ArchiveQueue {
+        &mut self.archive
+    }
+
+    fn get_latest(&mut self) -> Option<DateTime<Utc>> {
+        self.latest
+    }
+
+    fn set_latest(&mut self, tstamp: Option<DateTime<Utc>>) {
+        self.latest = tstamp;
+    }
+
+    fn get_out_of_order(&mut self) -> OutOfOrder {
+        self.options.out_of_order
     }
 
     fn get_writer(&mut self) -> Option<&mut File> {
@@ -125,8 +179,8 @@ impl<Kind: FmtInfo> UtcDailyBoundary for UtcSingleFrame<Kind> {
     }
 }
 
-impl<T: Serialize> UtcSingleFrame<Json<T>> {
-    /// Store a JSON-serialized data frame.
+impl<Kind: Encoder> UtcSingleFrame<Kind> {
+    /// Store a data frame.
     ///
     /// # Arguments:
     /// - `tstamp`: Timestamp of the data frame.
@@ -138,6 +192,7 @@ impl<T: Serialize> UtcSingleFrame<Json<T>> {
     ///
     /// # Errors:
     /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
+    /// - If the data cannot be encoded, an `std::io::Error` is returned.
     /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
     ///
     /// # Example:
@@ -160,66 +215,74 @@ impl<T: Serialize> UtcSingleFrame<Json<T>> {
     /// };
     /// let now = Utc::now();
     /// store.store(now, &data).unwrap();
-    /// ```    
-    pub fn store(&mut self, tstamp: DateTime<Utc>, data: &T) -> Result<(), std::io::Error> {
-        let filename = self.check_time_utcdaily::<Json<T>>(tstamp, true)?;
-        if filename.exists() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::AlreadyExists,
-                format!("File already exists: {filename:?}"),
-            ));
-        }
-        let mut writer = File::create(filename.get_filename())?;
-        let repr = serde_json::to_string(data)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
-        writer.write_all(repr.as_bytes())?;
+    /// ```
+    pub fn store(
+        &mut self,
+        tstamp: DateTime<Utc>,
+        data: &Kind::Frame,
+    ) -> Result<(), std::io::Error> {
+        let filename = self.next_frame(tstamp)?;
+        let mut writer = File::create(filename)?;
+        Kind::encode_single(&mut writer, data, &self.options)?;
         writer.flush()?;
+        self.register_utcdaily(tstamp);
         Ok(())
     }
 }
 
-impl UtcSingleFrame<Binary> {
-    /// Store a binary data frame.
+impl<T: FmtInfo> UtcSingleFrame<T> {
+    /// Store using a custom writer.
     ///
     /// # Arguments:
     /// - `tstamp`: Timestamp of the data frame.
-    /// - `data`: Data to be stored.
     ///
     /// # Returns:
-    /// - `Ok(())` if the data was stored successfully.
+    /// - `Ok(PathBuf)` if the target file does not exist, and can be created by the custom writer.
     /// - `Err(std::io::Error)` if there was an error during the process.
-    ///
-    /// # Errors:
-    /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
-    /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
-    ///
-    pub fn store(&mut self, tstamp: DateTime<Utc>, data: &[u8]) -> Result<(), std::io::Error> {
-        let filename = self.check_time_utcdaily::<Binary>(tstamp, true)?;
-        if filename.exists() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::AlreadyExists,
-                format!("File already exists: {filename:?}"),
-            ));
-        }
-        let writer = File::create(filename.get_filename())?;
-        store_binary(writer, data)?;
-        Ok(())
+    pub fn store_custom_writer(
+        &mut self,
+        tstamp: DateTime<Utc>,
+    ) -> Result<PathBuf, std::io::Error> {
+        let filename = self.next_frame(tstamp)?;
+        self.register_utcdaily(tstamp);
+        Ok(filename)
     }
-}
 
-impl<T: FmtInfo> UtcSingleFrame<T> {
-    /// Store using a custom writer.
+    /// Store using a managed writer.
     ///
     /// # Arguments:
     /// - `tstamp`: Timestamp of the data frame.
     ///
     /// # Returns:
-    /// - `Ok(PathBuf)` if the target file does not exist, and can be created by the custom writer.
+    /// - `Ok(FrameWriter)` if the target file does not exist. The frame is stored, and
+    ///   registered as the latest frame of the store, once [FrameWriter::commit] is called.
+    ///   It is discarded if the writer is dropped.
     /// - `Err(std::io::Error)` if there was an error during the process.
-    pub fn store_custom_writer(
+    ///
+    /// # Example:
+    /// ```rust,no_run
+    /// use chrono::Utc;
+    /// use std::{io::Write, path::PathBuf};
+    /// use datastor::{UtcSingleFrame, Binary};
+    ///
+    /// let mut store = UtcSingleFrame::<Binary>::new(PathBuf::from("test_utcsingleframe"), true).unwrap();
+    /// let mut writer = store.store_frame_writer(Utc::now()).unwrap();
+    /// writer.write_all(b"Hello").unwrap();
+    /// let path = writer.commit().unwrap();
+    /// ```
+    pub fn store_frame_writer(
         &mut self,
         tstamp: DateTime<Utc>,
-    ) -> Result<PathBuf, std::io::Error> {
+    ) -> Result<FrameWriter<'_>, std::io::Error> {
+        let filename = self.next_frame(tstamp)?;
+        FrameWriter::new(
+            filename,
+            Some(Box::new(move || self.register_utcdaily(tstamp))),
+        )
+    }
+
+    /// Route a frame to its directory, and get the name of its file.
+    fn next_frame(&mut self, tstamp: DateTime<Utc>) -> Result<PathBuf, std::io::Error> {
         let filename = self.check_time_utcdaily::<T>(tstamp, true)?;
         if filename.exists() {
             return Err(std::io::Error::new(
@@ -237,12 +300,27 @@ impl<T: FmtInfo> UtcSingleFrame<T> {
 /// /path/to/root/<exec count [u32]>/<frame number [u64]>.{EXTENSION}
 ///
 /// New files are created for each frame submitted using the `store` method.
+/// An existing run can be resumed using [StoreOptions::run], in which case frames
+/// are numbered after the latest frame of the run.
 pub struct ExecCountSingleFrame<Kind> {
     root_dir: PathBuf,
     framecount: u64,
+    options: StoreOptions,
+    manifest: RunManifest,
+    custom: Option<(PathBuf, Option<Duration>)>,
     _marker: PhantomData<Kind>,
 }
 
+impl<Kind> Drop for ExecCountSingleFrame<Kind> {
+    fn drop(&mut self) {
+        self.manifest
+            .custom_frame(&self.root_dir, self.custom.take());
+        if let Err(e) = self.manifest.close(&self.root_dir) {
+            log::warn!("Error closing the manifest of {:?}: {e:?}", self.root_dir);
+        }
+    }
+}
+
 impl<Kind: FmtInfo> ExecCountSingleFrame<Kind> {
     #[must_use = "The errors must be handled."]
     /// Create a new storage configuration.
@@ -250,48 +328,82 @@ impl<Kind: FmtInfo> ExecCountSingleFrame<Kind> {
     /// # Arguments:
     /// - `root_dir`: Root directory where data files will be stored.
     pub fn new(root_dir: &str) -> Result<Self, std::io::Error> {
+        Self::new_with_options(root_dir, StoreOptions::default())
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `options`: Options applied to the files created by this store.
+    pub fn new_with_options(root_dir: &str, options: StoreOptions) -> Result<Self, std::io::Error> {
         std::fs::create_dir_all(root_dir)?;
-        let runcount1 = (find_max_iter(root_dir, None)? as u32)
-            .checked_add(1)
-            .ok_or({
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Failed to increment run count",
-                )
-            })?;
-        let runcount2 = (find_max_iter(root_dir, Some(OsStr::new("gz")))? as u32)
-            .checked_add(1)
-            .ok_or({
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Failed to increment run count",
-                )
-            })?;
-        let runcount = runcount1.max(runcount2);
-        let root_dir = PathBuf::from(root_dir).join(format!("{runcount:0>10}"));
+        let (root_dir, _) = run_dir(root_dir, options.run)?;
         std::fs::create_dir_all(&root_dir)?;
-        let framecount = 0;
+        // resume after the latest frame of the run, if any
+        let extension = OsStr::new(Kind::extension());
+        let framecount = find_last(&root_dir, Some(extension))?.map_or(0, |(last, _)| last);
+        let manifest =
+            RunManifest::start::<Kind>(&root_dir, None, "ExecCountSingleFrame", &options)?;
 
         Ok(Self {
             root_dir,
             framecount,
+            options,
+            manifest,
+            custom: None,
             _marker: PhantomData,
         })
     }
 
     // Store using a custom writer.
     ///
+    /// The frame is recorded in the run manifest when the next frame is stored, or
+    /// when the store is dropped, if the custom writer has created the file by then.
+    ///
     /// # Returns:
     /// - `Ok(PathBuf)` if the target file does not exist, and can be created by the custom writer.
     /// - `Err(std::io::Error)` if there was an error during the process.
     pub fn store_custom_writer(&mut self) -> Result<PathBuf, std::io::Error> {
+        let (filename, fileidx) = self.next_frame()?;
+        self.framecount = fileidx;
+        self.custom = Some((filename.clone(), None));
+        Ok(filename)
+    }
+
+    /// Store using a managed writer.
+    ///
+    /// # Returns:
+    /// - `Ok(FrameWriter)` if the target file does not exist. The frame is stored, and
+    ///   the frame count is incremented, once [FrameWriter::commit] is called.
+    /// - `Err(std::io::Error)` if there was an error during the process.
+    pub fn store_frame_writer(&mut self) -> Result<FrameWriter<'_>, std::io::Error> {
+        let (filename, fileidx) = self.next_frame()?;
+        let commit = filename.clone();
+        FrameWriter::new(
+            filename,
+            Some(Box::new(move || {
+                self.framecount = fileidx;
+                self.manifest.single_frame(&self.root_dir, &commit, None);
+            })),
+        )
+    }
+
+    /// Manifest of the run, as written to the run directory when the store is dropped.
+    pub fn manifest(&self) -> &RunManifest {
+        &self.manifest
+    }
+
+    fn next_frame(&mut self) -> Result<(PathBuf, u64), std::io::Error> {
+        self.manifest
+            .custom_frame(&self.root_dir, self.custom.take());
         let fileidx = self.framecount.checked_add(1).ok_or({
             std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 "Failed to increment frame count",
             )
         })?;
-        self.framecount = fileidx;
         let filename = self
             .root_dir
             .join(format!("{:0>20}.{}", fileidx, Kind::extension()));
@@ -301,7 +413,7 @@ impl<Kind: FmtInfo> ExecCountSingleFrame<Kind> {
                 format!("File already exists: {filename:?}"),
             ));
         }
-        Ok(filename)
+        Ok((filename, fileidx))
     }
 }
 
@@ -310,7 +422,12 @@ impl<Kind: FmtInfo> ExecCountSingleFrame<Kind> {
 /// structure relative to the root as follows:
 /// /path/to/root/<exec count [u32]>/<day count [u32]>/<frame number [u64]>.{EXTENSION}
 ///
-/// New files are created for each frame submitted using the `store` method.
+/// New files are created for each frame submitted using the `store` method,
+/// or the `store_now` method, which measures the time elapsed since the store
+/// was created itself.
+///
+/// An existing run can be resumed using [StoreOptions::run], in which case frames
+/// are numbered after the latest frame of the latest day of the run.
 pub struct ExecCountDailySingleFrame<Kind> {
     root_dir: PathBuf,
     daycount: u32,
@@ -318,11 +435,28 @@ pub struct ExecCountDailySingleFrame<Kind> {
     compress_tx: Option<mpsc::Sender<Option<PathBuf>>>,
     compress_hdl: Option<JoinHandle<()>>,
     last_dir: PathBuf,
+    options: StoreOptions,
+    start: Instant,
+    offset: Duration,
+    manifest: RunManifest,
+    custom: Option<(PathBuf, Option<Duration>)>,
     _marker: PhantomData<Kind>,
 }
 
 impl<Kind> Drop for ExecCountDailySingleFrame<Kind> {
     fn drop(&mut self) {
+        self.manifest
+            .custom_frame(&self.root_dir, self.custom.take());
+        let anchor = Anchor {
+            elapsed: self.offset + self.start.elapsed(),
+            utc: Utc::now(),
+        };
+        if let Err(e) = self.manifest.push_anchor(anchor) {
+            log::warn!("Error anchoring the run {:?}: {e:?}", self.root_dir);
+        }
+        if let Err(e) = self.manifest.close(&self.root_dir) {
+            log::warn!("Error closing the manifest of {:?}: {e:?}", self.root_dir);
+        }
         if let Some(tx) = &self.compress_tx {
             let _ = tx.send(None);
         }
@@ -340,48 +474,77 @@ impl<Kind: FmtInfo> ExecCountDailySingleFrame<Kind> {
     /// - `root_dir`: Root directory where data files will be stored.
     /// - `compress`: Whether individual, hourly files will be compressed into a tarball at the end of the day.
     pub fn new(root_dir: &str, compress: bool) -> Result<Self, std::io::Error> {
+        Self::new_with_options(root_dir, compress, StoreOptions::default())
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether individual, hourly files will be compressed into a tarball at the end of the day.
+    /// - `options`: Options applied to the files created by this store.
+    pub fn new_with_options(
+        root_dir: &str,
+        compress: bool,
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
         std::fs::create_dir_all(root_dir)?;
         lazy_static! {
             static ref COMPRESSION_THREAD_TX: Arc<Mutex<Option<mpsc::Sender<Option<PathBuf>>>>> =
                 Arc::new(Mutex::new(None));
         }
-        let runcount = (find_max_iter(root_dir, None)? as u32)
-            .checked_add(1)
-            .ok_or({
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Failed to increment run count",
-                )
-            })?
-            .max(find_max_iter(root_dir, Some(OsStr::new("gz")))? as u32)
-            .checked_add(1)
-            .ok_or({
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Failed to increment run count",
-                )
-            })?;
-        let root_dir = PathBuf::from(root_dir).join(format!("{runcount:0>10}"));
+        let (root_dir, resumed) = run_dir(root_dir, options.run)?;
         std::fs::create_dir_all(&root_dir)?;
-        let last_dir = root_dir.join(format!("{:0>10}", 0));
-        std::fs::create_dir_all(&last_dir)?;
+        let manifest =
+            RunManifest::start::<Kind>(&root_dir, None, "ExecCountDailySingleFrame", &options)?;
         // handle compression
         let (compress_tx, compress_hdl) =
             get_compressor(compress, (*COMPRESSION_THREAD_TX).clone());
 
-        Ok(Self {
+        let mut store = Self {
+            last_dir: root_dir.join(format!("{:0>10}", 0)),
             root_dir,
             daycount: 0,
             framecount: 0,
             compress_tx,
             compress_hdl,
-            last_dir,
+            options,
+            start: Instant::now(),
+            offset: Duration::ZERO,
+            manifest,
+            custom: None,
             _marker: PhantomData,
-        })
+        };
+        if resumed {
+            store.resume()?;
+        }
+        std::fs::create_dir_all(&store.last_dir)?;
+        store.anchor()?;
+        Ok(store)
+    }
+
+    /// Pick up the latest frame of a resumed run, and the elapsed time of the run.
+    fn resume(&mut self) -> Result<(), std::io::Error> {
+        let Some((daycount, day_dir)) = find_last(&self.root_dir, None)? else {
+            return Ok(());
+        };
+        self.daycount = daycount as u32;
+        self.last_dir = day_dir;
+        let extension = OsStr::new(Kind::extension());
+        if let Some((framecount, last)) = find_last(&self.last_dir, Some(extension))? {
+            self.framecount = framecount as u32;
+            let start = Duration::from_secs(daycount * 24 * 3600);
+            self.offset = resumed_elapsed(&self.manifest, start, &last);
+        }
+        Ok(())
     }
 
     /// Store using a custom writer.
     ///
+    /// The frame is recorded in the run manifest when the next frame is stored, or
+    /// when the store is dropped, if the custom writer has created the file by then.
+    ///
     /// # Arguments:
     /// - `tdelta`: Time delta since the beginning of execution.
     ///
@@ -389,16 +552,97 @@ impl<Kind: FmtInfo> ExecCountDailySingleFrame<Kind> {
     /// - `Ok(PathBuf)` if the target file does not exist, and can be created by the custom writer.
     /// - `Err(std::io::Error)` if there was an error during the process.
     pub fn store_custom_writer(&mut self, tdelta: &Duration) -> Result<PathBuf, std::io::Error> {
+        let (filename, daycount, fileidx) = self.next_frame(tdelta)?;
+        self.commit_frame(daycount, fileidx);
+        self.custom = Some((filename.clone(), Some(*tdelta)));
+        Ok(filename)
+    }
+
+    /// Store using a managed writer.
+    ///
+    /// # Arguments:
+    /// - `tdelta`: Time delta since the beginning of execution.
+    ///
+    /// # Returns:
+    /// - `Ok(FrameWriter)` if the target file does not exist. The frame is stored, and
+    ///   the frame count is incremented, once [FrameWriter::commit] is called. The store
+    ///   only rolls over to a new day once the first frame of that day is committed.
+    /// - `Err(std::io::Error)` if there was an error during the process.
+    pub fn store_frame_writer(
+        &mut self,
+        tdelta: &Duration,
+    ) -> Result<FrameWriter<'_>, std::io::Error> {
+        let (filename, daycount, fileidx) = self.next_frame(tdelta)?;
+        let (commit, tdelta) = (filename.clone(), *tdelta);
+        FrameWriter::new(
+            filename,
+            Some(Box::new(move || {
+                self.commit_frame(daycount, fileidx);
+                self.manifest
+                    .single_frame(&self.root_dir, &commit, Some(tdelta));
+            })),
+        )
+    }
+
+    /// Manifest of the run, as written to the run directory when the store is dropped.
+    pub fn manifest(&self) -> &RunManifest {
+        &self.manifest
+    }
+
+    /// Record an anchor relating the elapsed time measured by the store, see
+    /// [ExecCountDailySingleFrame::elapsed], to the wall clock, and write it to the run manifest.
+    ///
+    /// Anchors are also recorded when the store is created and dropped, and
+    /// periodically when frames are stored, see [StoreOptions::anchor_interval].
+    ///
+    /// # Errors:
+    /// - If the run manifest cannot be written.
+    pub fn anchor(&mut self) -> Result<Anchor, std::io::Error> {
+        let anchor = Anchor {
+            elapsed: self.elapsed(),
+            utc: Utc::now(),
+        };
+        self.record_anchor(anchor)?;
+        Ok(anchor)
+    }
+
+    /// Record an anchor measured by the caller, e.g. for the elapsed times passed to
+    /// the store methods when they are not measured by the store, and write it to the
+    /// run manifest.
+    ///
+    /// # Errors:
+    /// - If the elapsed time of the anchor is earlier than the latest anchor, an
+    ///   `std::io::Error` with `InvalidInput` kind is returned.
+    /// - If the run manifest cannot be written.
+    pub fn record_anchor(&mut self, anchor: Anchor) -> Result<(), std::io::Error> {
+        self.manifest.anchor(&self.root_dir, anchor)
+    }
+
+    /// Time elapsed since the store was created, measured using a monotonic clock.
+    /// If the run was resumed, the time elapsed is counted from the start of the run.
+    ///
+    /// The elapsed time can be passed to the methods taking the time delta
+    /// since the beginning of execution, e.g. `store.store_frame_writer(&store.elapsed())`.
+    pub fn elapsed(&self) -> Duration {
+        self.offset + self.start.elapsed()
+    }
+
+    /// Get the next frame file name, day count and index, creating the directory of a new day.
+    ///
+    /// The state of the store is only updated once the frame is committed, see
+    /// [ExecCountDailySingleFrame::commit_frame].
+    fn next_frame(&mut self, tdelta: &Duration) -> Result<(PathBuf, u32, u32), std::io::Error> {
+        self.manifest
+            .custom_frame(&self.root_dir, self.custom.take());
+        if self.manifest.anchor_due(self.elapsed(), &self.options) {
+            self.anchor()?;
+        }
         let tdelta = tdelta.as_secs_f64();
         let daycount = (tdelta / (24.0 * 3600.0)).floor() as u32;
-        if daycount > self.daycount {
-            if let Some(tx) = &self.compress_tx {
-                let _ = tx.send(Some(self.last_dir.clone()));
-            }
-            self.framecount = 0;
-            self.daycount = daycount;
-            self.last_dir = PathBuf::from(&self.root_dir).join(format!("{:0>10}", self.daycount));
-            std::fs::create_dir_all(&self.last_dir)?;
+        let (dir, fileidx) = if daycount > self.daycount {
+            let dir = PathBuf::from(&self.root_dir).join(format!("{daycount:0>10}"));
+            std::fs::create_dir_all(&dir)?;
+            (dir, 0)
         } else {
             let fileidx = self.framecount.checked_add(1).ok_or({
                 std::io::Error::new(
@@ -406,50 +650,35 @@ impl<Kind: FmtInfo> ExecCountDailySingleFrame<Kind> {
                     "Failed to increment frame count",
                 )
             })?;
-            self.framecount = fileidx;
-        }
-        let filename =
-            self.last_dir
-                .join(format!("{:0>10}.{}", self.framecount, Kind::extension()));
+            (self.last_dir.clone(), fileidx)
+        };
+        let filename = dir.join(format!("{:0>10}.{}", fileidx, Kind::extension()));
         if filename.exists() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::AlreadyExists,
                 format!("File already exists: {filename:?}"),
             ));
         }
-        Ok(filename)
+        Ok((filename, daycount.max(self.daycount), fileidx))
     }
-}
 
-impl<T: Serialize> ExecCountSingleFrame<Json<T>> {
-    /// Store a JSON-serialized data frame.
-    ///
-    /// # Arguments:
-    /// - `data`: Data to be stored.
-    ///
-    /// # Returns:
-    /// - `Ok(())` if the data was stored successfully.
-    /// - `Err(std::io::Error)` if there was an error during the process.
-    ///
-    /// # Errors:
-    /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
-    /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
-    ///
-    pub fn store(&mut self, data: &T) -> core::result::Result<(), std::io::Error> {
-        let filename = self.store_custom_writer()?;
-        let mut writer = std::fs::File::create(filename)?;
-        serde_json::to_writer(&mut writer, data)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
-        writer.flush()?;
-        Ok(())
+    /// Register a stored frame, rolling over to a new day if required.
+    fn commit_frame(&mut self, daycount: u32, fileidx: u32) {
+        if daycount > self.daycount {
+            if let Some(tx) = &self.compress_tx {
+                let _ = tx.send(Some(self.last_dir.clone()));
+            }
+            self.daycount = daycount;
+            self.last_dir = PathBuf::from(&self.root_dir).join(format!("{daycount:0>10}"));
+        }
+        self.framecount = fileidx;
     }
 }
 
-impl<T: Serialize> ExecCountDailySingleFrame<Json<T>> {
-    /// Store a JSON-serialized data frame.
+impl<Kind: Encoder> ExecCountSingleFrame<Kind> {
+    /// Store a data frame.
     ///
     /// # Arguments:
-    /// - `tdelta`: Time delta since the beginning
     /// - `data`: Data to be stored.
     ///
     /// # Returns:
@@ -458,24 +687,22 @@ impl<T: Serialize> ExecCountDailySingleFrame<Json<T>> {
     ///
     /// # Errors:
     /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
+    /// - If the data cannot be encoded, an `std::io::Error` is returned.
     /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
     ///
-    pub fn store(
-        &mut self,
-        tdelta: &Duration,
-        data: &T,
-    ) -> core::result::Result<(), std::io::Error> {
-        let filename = self.store_custom_writer(tdelta)?;
-        let mut writer = std::fs::File::create(filename)?;
-        serde_json::to_writer(&mut writer, data)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+    pub fn store(&mut self, data: &Kind::Frame) -> Result<(), std::io::Error> {
+        let (filename, fileidx) = self.next_frame()?;
+        self.framecount = fileidx;
+        let mut writer = File::create(&filename)?;
+        Kind::encode_single(&mut writer, data, &self.options)?;
         writer.flush()?;
+        self.manifest.single_frame(&self.root_dir, &filename, None);
         Ok(())
     }
 }
 
-impl ExecCountSingleFrame<Binary> {
-    /// Store a binary data frame.
+impl<Kind: Encoder> ExecCountDailySingleFrame<Kind> {
+    /// Store a data frame stamped with the time elapsed since the store was created.
     ///
     /// # Arguments:
     /// - `data`: Data to be stored.
@@ -486,18 +713,13 @@ impl ExecCountSingleFrame<Binary> {
     ///
     /// # Errors:
     /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
+    /// - If the data cannot be encoded, an `std::io::Error` is returned.
     /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
-    ///
-    pub fn store(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
-        let filename = self.store_custom_writer()?;
-        let writer = File::create(filename)?;
-        store_binary(writer, data)?;
-        Ok(())
+    pub fn store_now(&mut self, data: &Kind::Frame) -> Result<(), std::io::Error> {
+        self.store(&self.elapsed(), data)
     }
-}
 
-impl ExecCountDailySingleFrame<Binary> {
-    /// Store a binary data frame.
+    /// Store a data frame.
     ///
     /// # Arguments:
     /// - `tdelta`: Time delta since the beginning of execution.
@@ -509,12 +731,24 @@ impl ExecCountDailySingleFrame<Binary> {
     ///
     /// # Errors:
     /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
+    /// - If the data cannot be encoded, an `std::io::Error` is returned.
     /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
     ///
-    pub fn store(&mut self, tdelta: &Duration, data: &[u8]) -> Result<(), std::io::Error> {
-        let filename = self.store_custom_writer(tdelta)?;
-        let writer = File::create(filename)?;
-        store_binary(writer, data)?;
+    pub fn store(&mut self, tdelta: &Duration, data: &Kind::Frame) -> Result<(), std::io::Error> {
+        let (filename, daycount, fileidx) = self.next_frame(tdelta)?;
+        let write = || {
+            let mut writer = File::create(&filename)?;
+            Kind::encode_single(&mut writer, data, &self.options)?;
+            writer.flush()
+        };
+        if let Err(e) = write() {
+            // the frame is not stored, so that the file name can be used again
+            std::fs::remove_file(&filename).unwrap_or_default();
+            return Err(e);
+        }
+        self.commit_frame(daycount, fileidx);
+        self.manifest
+            .single_frame(&self.root_dir, &filename, Some(*tdelta));
         Ok(())
     }
 }
@@ -546,6 +780,31 @@ mod test {
         std::fs::remove_dir_all(dir).unwrap_or_default();
     }
 
+    #[test]
+    fn test_utc_frame_writer() {
+        use crate::OutOfOrder;
+        use chrono::{Duration, TimeZone};
+        use std::io::Write;
+        let dir = PathBuf::from("test_utc_frame_writer");
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+        let options = StoreOptions::new().out_of_order(OutOfOrder::Reject);
+        let mut store =
+            UtcSingleFrame::<Binary>::new_with_options(dir.clone(), false, options).unwrap();
+        let day1 = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let day2 = day1 + Duration::days(1);
+        let mut writer = store.store_frame_writer(day2).unwrap();
+        writer.write_all(b"discarded").unwrap();
+        drop(writer);
+        // the discarded frame is not registered with the store
+        store.store(day1, &[1]).unwrap();
+        let mut writer = store.store_frame_writer(day2).unwrap();
+        writer.write_all(b"committed").unwrap();
+        writer.commit().unwrap();
+        let err = store.store(day1 + Duration::hours(1), &[2]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
     #[test]
     fn test_exec_count_singleframe() {
         let dir = PathBuf::from("test_exec_count_singleframe");
@@ -559,6 +818,27 @@ mod test {
         std::fs::remove_dir_all(dir).unwrap_or_default();
     }
 
+    #[test]
+    fn test_frame_writer() {
+        use std::io::Write;
+        let dir = PathBuf::from("test_frame_writer");
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+        let mut store = ExecCountSingleFrame::<Binary>::new(dir.to_str().unwrap()).unwrap();
+        let mut writer = store.store_frame_writer().unwrap();
+        writer.write_all(b"discarded").unwrap();
+        drop(writer); // not committed
+        let mut writer = store.store_frame_writer().unwrap();
+        writer.write_all(b"committed").unwrap();
+        let path = writer.commit().unwrap();
+        assert_eq!(path.file_stem().unwrap(), format!("{:0>20}", 1).as_str());
+        assert_eq!(std::fs::read(&path).unwrap(), b"committed");
+        let files = std::fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(files, 2); // the frame and the manifest, no temporary files left behind
+        let path = store.store_custom_writer().unwrap();
+        assert_eq!(path.file_stem().unwrap(), format!("{:0>20}", 2).as_str());
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
     #[test]
     fn test_exec_count_daily_singleframe() {
         use std::time::Duration;
@@ -579,4 +859,118 @@ mod test {
             .unwrap(); // sixth frame
         std::fs::remove_dir_all(dir).unwrap_or_default();
     }
+
+    #[test]
+    fn test_resume_singleframe() {
+        use crate::Run;
+        use std::time::Duration;
+        let dir = PathBuf::from("test_resume_singleframe");
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+        let root = dir.to_str().unwrap();
+        let mut store = ExecCountSingleFrame::<Binary>::new(root).unwrap();
+        store.store(&[1]).unwrap();
+        store.store(&[2]).unwrap();
+        drop(store);
+        let options = StoreOptions::new().run(Run::Latest);
+        let mut store = ExecCountSingleFrame::<Binary>::new_with_options(root, options).unwrap();
+        let path = store.store_custom_writer().unwrap();
+        assert_eq!(path, dir.join(format!("{:0>10}/{:0>20}.bin", 1, 3)));
+
+        let dir = dir.join("daily");
+        let root = dir.to_str().unwrap();
+        let mut store = ExecCountDailySingleFrame::<Binary>::new(root, false).unwrap();
+        store.store(&Duration::from_secs(1), &[1]).unwrap();
+        store.store(&Duration::from_secs(86400), &[2]).unwrap();
+        drop(store);
+        let options = StoreOptions::new().run(Run::Latest);
+        let mut store =
+            ExecCountDailySingleFrame::<Binary>::new_with_options(root, false, options).unwrap();
+        assert!(store.elapsed() >= Duration::from_secs(86400));
+        let path = store.store_custom_writer(&store.elapsed()).unwrap();
+        assert_eq!(
+            path,
+            dir.join(format!("{:0>10}/{:0>10}/{:0>10}.bin", 1, 1, 1))
+        );
+        std::fs::remove_dir_all("test_resume_singleframe").unwrap_or_default();
+    }
+
+    #[test]
+    fn test_daily_frame_writer_rollover() {
+        use std::time::Duration;
+        let dir = PathBuf::from("test_daily_frame_writer_rollover");
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+        let mut store =
+            ExecCountDailySingleFrame::<Binary>::new(dir.to_str().unwrap(), false).unwrap();
+        let run = dir.join(format!("{:0>10}", 1));
+        let frame = |day: u32, idx: u32| run.join(format!("{day:0>10}/{idx:0>10}.bin"));
+        store.store(&Duration::from_secs(1), &[1]).unwrap();
+        store.store(&Duration::from_secs(2), &[2]).unwrap();
+        // a writer dropped after crossing the day boundary leaves the store untouched
+        let writer = store
+            .store_frame_writer(&Duration::from_secs(86400))
+            .unwrap();
+        assert_eq!(writer.path(), frame(1, 0));
+        drop(writer);
+        let path = store.store_custom_writer(&Duration::from_secs(3)).unwrap();
+        assert_eq!(path, frame(0, 3));
+        let writer = store
+            .store_frame_writer(&Duration::from_secs(86401))
+            .unwrap();
+        assert_eq!(writer.commit().unwrap(), frame(1, 0));
+        let path = store
+            .store_custom_writer(&Duration::from_secs(86402))
+            .unwrap();
+        assert_eq!(path, frame(1, 1));
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_daily_store_error() {
+        use crate::Json;
+        use std::{collections::BTreeMap, time::Duration};
+        let dir = PathBuf::from("test_daily_store_error");
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+        // maps with non-string keys cannot be serialized to JSON
+        let mut store = ExecCountDailySingleFrame::<Json<BTreeMap<Vec<u8>, u32>>>::new(
+            dir.to_str().unwrap(),
+            false,
+        )
+        .unwrap();
+        let run = dir.join(format!("{:0>10}", 1));
+        let frame = |day: u32, idx: u32| run.join(format!("{day:0>10}/{idx:0>10}.json"));
+        store
+            .store(&Duration::from_secs(1), &BTreeMap::new())
+            .unwrap();
+        let invalid = BTreeMap::from([(vec![1], 1)]);
+        assert!(store.store(&Duration::from_secs(86400), &invalid).is_err());
+        assert!(!frame(1, 0).exists());
+        // the store stays on the same day, and can store the next frame
+        store
+            .store(&Duration::from_secs(2), &BTreeMap::new())
+            .unwrap();
+        assert!(frame(0, 2).exists());
+        assert!(store.store(&Duration::from_secs(3), &invalid).is_err());
+        assert!(!frame(0, 3).exists());
+        store
+            .store(&Duration::from_secs(86400), &BTreeMap::new())
+            .unwrap();
+        assert!(frame(1, 0).exists());
+        drop(store);
+        assert_eq!(RunManifest::read(&run).unwrap().frames, 3);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_raw_singleframe() {
+        use crate::Raw;
+        let dir = PathBuf::from("test_raw_singleframe");
+        std::fs::remove_dir_all(&dir).unwrap_or_default();
+        let mut store = ExecCountSingleFrame::<Raw>::new(dir.to_str().unwrap()).unwrap();
+        store.store(b"serial capture").unwrap();
+        let path = dir
+            .join(format!("{:0>10}", 1))
+            .join(format!("{:0>20}.raw", 1));
+        assert_eq!(std::fs::read(path).unwrap(), b"serial capture");
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
 }
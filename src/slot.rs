@@ -0,0 +1,313 @@
+use chrono::{
+    DateTime, Datelike, DurationRound, LocalResult, Months, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeDelta, TimeZone, Timelike,
+};
+
+use crate::TimeSlot;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Length of the time slots covered by the files of a [Slotted](crate::Slotted) store.
+pub enum SlotLength {
+    /// Slots of any positive length, aligned to the Unix epoch.
+    Duration(TimeDelta),
+    /// Calendar minutes.
+    Minute,
+    /// Calendar hours.
+    Hour,
+    /// Calendar days.
+    Day,
+    /// ISO 8601 weeks, starting on Monday.
+    IsoWeek,
+    /// Calendar months.
+    Month,
+}
+
+impl SlotLength {
+    /// Make sure the slot length is positive.
+    pub(crate) fn validate(self) -> Result<Self, std::io::Error> {
+        match self {
+            SlotLength::Duration(span) if span <= TimeDelta::zero() => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Slot length must be positive, got {span}"),
+            )),
+            _ => Ok(self),
+        }
+    }
+
+    /// Length of the slot in elapsed time, for slots of at most a day that do not
+    /// depend on the calendar.
+    pub(crate) fn elapsed(&self) -> Option<std::time::Duration> {
+        let length = match self {
+            SlotLength::Duration(span) => span.to_std().ok()?,
+            SlotLength::Minute => std::time::Duration::from_secs(60),
+            SlotLength::Hour => std::time::Duration::from_secs(3600),
+            SlotLength::Day => std::time::Duration::from_secs(24 * 3600),
+            SlotLength::IsoWeek | SlotLength::Month => return None,
+        };
+        (length <= std::time::Duration::from_secs(24 * 3600)).then_some(length)
+    }
+
+    /// Start of the slot containing `tstamp`, in the time zone of `tstamp`.
+    ///
+    /// Calendar units start at local wall clock boundaries. If a local hour or
+    /// minute occurs twice when clocks are turned back, each occurrence is a
+    /// separate slot. Calendar days, weeks and months start at the first
+    /// occurrence of their local midnight, or at the end of the gap if their
+    /// local midnight is skipped when clocks are turned forward.
+    pub(crate) fn start<Tz: TimeZone>(&self, tstamp: &DateTime<Tz>) -> DateTime<Tz> {
+        let local = tstamp.naive_local();
+        let midnight =
+            |date: NaiveDate| resolve(&tstamp.timezone(), date.and_time(NaiveTime::MIN), None);
+        // truncated using the fields of the wall clock time, which is valid for any date
+        let minute = |minute: u32| {
+            let time = NaiveTime::from_hms_opt(local.hour(), minute, 0).unwrap_or(local.time());
+            resolve(
+                &tstamp.timezone(),
+                local.date().and_time(time),
+                Some(tstamp),
+            )
+        };
+        match self {
+            SlotLength::Duration(span) => match tstamp.to_utc().duration_trunc(*span) {
+                Ok(start) => start.with_timezone(&tstamp.timezone()),
+                Err(_) => tstamp.clone(),
+            },
+            SlotLength::Minute => minute(local.minute()),
+            SlotLength::Hour => minute(0),
+            SlotLength::Day => midnight(local.date()),
+            SlotLength::IsoWeek => midnight(
+                local.date() - TimeDelta::days(local.weekday().num_days_from_monday() as i64),
+            ),
+            SlotLength::Month => midnight(local.date().with_day(1).unwrap()),
+        }
+    }
+
+    /// End of the slot starting at `start`, which is the start of the next slot.
+    ///
+    /// Calendar days may last 23 or 25 hours when clocks are changed.
+    pub(crate) fn end<Tz: TimeZone>(&self, start: &DateTime<Tz>) -> DateTime<Tz> {
+        let midnight =
+            |date: NaiveDate| resolve(&start.timezone(), date.and_time(NaiveTime::MIN), None);
+        let date = start.naive_local().date();
+        match self {
+            SlotLength::Duration(span) => start.clone() + *span,
+            SlotLength::Minute => self.start(&(start.clone() + TimeDelta::minutes(1))),
+            SlotLength::Hour => self.start(&(start.clone() + TimeDelta::hours(1))),
+            SlotLength::Day => midnight(date + TimeDelta::days(1)),
+            SlotLength::IsoWeek => midnight(date + TimeDelta::weeks(1)),
+            SlotLength::Month => midnight(date + Months::new(1)),
+        }
+    }
+
+    /// Time slot containing `tstamp`.
+    pub(crate) fn slot<Tz: TimeZone>(&self, tstamp: &DateTime<Tz>) -> TimeSlot {
+        let start = self.start(tstamp);
+        TimeSlot::Utc {
+            end: self.end(&start).to_utc(),
+            start: start.to_utc(),
+        }
+    }
+}
+
+/// Instant at which the local wall clock time `local` starts in time zone `tz`.
+///
+/// If `local` occurs twice, the latest occurrence not after `not_after` is
+/// used, or the first occurrence if `not_after` is not given. If `local` is
+/// skipped, the instant at which the wall clock jumps past it is used.
+fn resolve<Tz: TimeZone>(
+    tz: &Tz,
+    local: NaiveDateTime,
+    not_after: Option<&DateTime<Tz>>,
+) -> DateTime<Tz> {
+    let mut naive = local;
+    loop {
+        match tz.from_local_datetime(&naive) {
+            LocalResult::Single(tstamp) => return tstamp,
+            LocalResult::Ambiguous(first, second) => {
+                return match not_after {
+                    Some(limit) if second <= *limit => second,
+                    _ => first,
+                }
+            }
+            // skipped, move on to the end of the gap
+            LocalResult::None => naive += TimeDelta::minutes(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Grouping of the files of a [Slotted](crate::Slotted) store into directories.
+///
+/// Files are grouped by the start of their time slot. Stores with a
+/// [PathTemplate](crate::PathTemplate) are grouped by the directories of the template instead. If compression is enabled,
+/// a directory is compressed into a tarball once the store moves on to the next
+/// directory.
+pub enum Grouping {
+    /// All files are stored in the root directory, and are not compressed.
+    Flat,
+    /// One directory per day, `YYYYMMDD`.
+    Day,
+    /// One directory per ISO 8601 week, `YYYYWww`.
+    IsoWeek,
+    /// One directory per month, `YYYYMM`.
+    Month,
+    /// One directory per year, `YYYY`.
+    Year,
+}
+
+impl Grouping {
+    /// `strftime` format of the name of the directory of a file, by the start of its time slot.
+    pub(crate) fn format(&self) -> Option<&'static str> {
+        match self {
+            Grouping::Flat => None,
+            Grouping::Day => Some("%Y%m%d"),
+            Grouping::IsoWeek => Some("%GW%V"),
+            Grouping::Month => Some("%Y%m"),
+            Grouping::Year => Some("%Y"),
+        }
+    }
+}
+
+/// Slot length and directory grouping of a preset [Slotted](crate::Slotted) store,
+/// such as [UtcHourly](crate::UtcHourly) and [UtcDaily](crate::UtcDaily), or
+/// [ExecCountSlotted](crate::ExecCountSlotted) store, such as [ExecCountHourly](crate::ExecCountHourly).
+pub trait SlotPreset {
+    /// Name of the UTC store, recorded in the header of its files.
+    const NAME: &'static str;
+    /// Name of the store in other time zones, recorded in the header of its files.
+    const ZONED_NAME: &'static str;
+    /// Name of the store counting time from the beginning of execution, recorded
+    /// in the header of its files.
+    const ELAPSED_NAME: &'static str;
+    /// Length of the time slots.
+    const SLOT: SlotLength;
+    /// Grouping of the files into directories.
+    const GROUPING: Grouping;
+}
+
+#[derive(Debug)]
+/// Marker of [Slotted](crate::Slotted) stores whose slot length and grouping are
+/// chosen when the store is created.
+pub struct CustomSlots;
+
+#[derive(Debug)]
+/// Preset of minute files grouped into daily directories, used by
+/// [UtcMinutely](crate::UtcMinutely), [ZonedMinutely](crate::ZonedMinutely)
+/// and [ExecCountMinutely](crate::ExecCountMinutely).
+pub struct MinutelySlots;
+
+impl SlotPreset for MinutelySlots {
+    const NAME: &'static str = "UtcMinutely";
+    const ZONED_NAME: &'static str = "ZonedMinutely";
+    const ELAPSED_NAME: &'static str = "ExecCountMinutely";
+    const SLOT: SlotLength = SlotLength::Minute;
+    const GROUPING: Grouping = Grouping::Day;
+}
+
+#[derive(Debug)]
+/// Preset of hourly files grouped into daily directories, used by [UtcHourly](crate::UtcHourly)
+/// and [ExecCountHourly](crate::ExecCountHourly).
+pub struct HourlySlots;
+
+impl SlotPreset for HourlySlots {
+    const NAME: &'static str = "UtcHourly";
+    const ZONED_NAME: &'static str = "ZonedHourly";
+    const ELAPSED_NAME: &'static str = "ExecCountHourly";
+    const SLOT: SlotLength = SlotLength::Hour;
+    const GROUPING: Grouping = Grouping::Day;
+}
+
+#[derive(Debug)]
+/// Preset of daily files grouped into daily directories, used by [UtcDaily](crate::UtcDaily)
+/// and [ExecCountDaily](crate::ExecCountDaily).
+pub struct DailySlots;
+
+impl SlotPreset for DailySlots {
+    const NAME: &'static str = "UtcDaily";
+    const ZONED_NAME: &'static str = "ZonedDaily";
+    const ELAPSED_NAME: &'static str = "ExecCountDaily";
+    const SLOT: SlotLength = SlotLength::Day;
+    const GROUPING: Grouping = Grouping::Day;
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use chrono_tz::Europe::Amsterdam;
+
+    use super::*;
+
+    #[test]
+    fn test_slot_start() {
+        let tstamp = Utc.with_ymd_and_hms(2025, 1, 1, 12, 34, 56).unwrap(); // a Wednesday
+        let start = |slot: SlotLength| slot.start(&tstamp).to_rfc3339();
+        assert_eq!(
+            start(SlotLength::Duration(TimeDelta::minutes(10))),
+            "2025-01-01T12:30:00+00:00"
+        );
+        assert_eq!(start(SlotLength::Minute), "2025-01-01T12:34:00+00:00");
+        assert_eq!(start(SlotLength::Hour), "2025-01-01T12:00:00+00:00");
+        assert_eq!(start(SlotLength::Day), "2025-01-01T00:00:00+00:00");
+        assert_eq!(start(SlotLength::IsoWeek), "2024-12-30T00:00:00+00:00");
+        assert_eq!(start(SlotLength::Month), "2025-01-01T00:00:00+00:00");
+        let month = SlotLength::Month.start(&Utc.with_ymd_and_hms(2025, 2, 28, 1, 0, 0).unwrap());
+        assert_eq!(
+            SlotLength::Month.end(&month),
+            Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap()
+        );
+        let week = SlotLength::IsoWeek.start(&tstamp);
+        assert_eq!(
+            week.format(Grouping::IsoWeek.format().unwrap()).to_string(),
+            "2025W01"
+        );
+        assert!(SlotLength::Duration(TimeDelta::zero()).validate().is_err());
+        // valid timestamps out of the range of nanosecond timestamps
+        let far = Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap();
+        assert_eq!(
+            SlotLength::Minute.start(&far),
+            Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 0).unwrap()
+        );
+        assert_eq!(
+            SlotLength::Hour.start(&far),
+            Utc.with_ymd_and_hms(9999, 12, 31, 23, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_slot_dst() {
+        let length = |slot: SlotLength, tstamp: DateTime<Utc>| {
+            let start = slot.start(&tstamp.with_timezone(&Amsterdam));
+            (start.to_rfc3339(), slot.end(&start) - start)
+        };
+        // clocks are turned forward at 02:00 local time
+        let spring = Utc.with_ymd_and_hms(2025, 3, 30, 12, 0, 0).unwrap();
+        assert_eq!(
+            length(SlotLength::Day, spring),
+            ("2025-03-30T00:00:00+01:00".into(), TimeDelta::hours(23))
+        );
+        // clocks are turned back at 03:00 local time
+        let autumn = Utc.with_ymd_and_hms(2025, 10, 26, 12, 0, 0).unwrap();
+        assert_eq!(
+            length(SlotLength::Day, autumn),
+            ("2025-10-26T00:00:00+02:00".into(), TimeDelta::hours(25))
+        );
+        // the local hour 02:00 occurs twice
+        let first = Utc.with_ymd_and_hms(2025, 10, 26, 0, 30, 0).unwrap();
+        assert_eq!(
+            length(SlotLength::Hour, first),
+            ("2025-10-26T02:00:00+02:00".into(), TimeDelta::hours(1))
+        );
+        assert_eq!(
+            length(SlotLength::Hour, first + TimeDelta::hours(1)),
+            ("2025-10-26T02:00:00+01:00".into(), TimeDelta::hours(1))
+        );
+        // epoch aligned slots do not depend on the time zone
+        assert_eq!(
+            length(SlotLength::Duration(TimeDelta::hours(1)), first),
+            ("2025-10-26T02:00:00+02:00".into(), TimeDelta::hours(1))
+        );
+        let offset = chrono::FixedOffset::east_opt(5 * 3600 + 1800).unwrap();
+        let start = SlotLength::Hour.start(&first.with_timezone(&offset));
+        assert_eq!(start.to_rfc3339(), "2025-10-26T06:00:00+05:30");
+    }
+}
@@ -0,0 +1,1452 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::{
+    borrow::Borrow,
+    fs::File,
+    io::{IoSlice, Write},
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+
+use crate::{
+    archive::ArchiveQueue,
+    batch::{BatchStore, BatchSummary},
+    formats::{store_binary_vectored, write_frame, FmtInfo},
+    framewriter::Framing,
+    index::IndexWriter,
+    lock::LockFile,
+    slot::{CustomSlots, DailySlots, Grouping, HourlySlots, MinutelySlots, SlotLength, SlotPreset},
+    template::{DiscoveredFile, PathTemplate},
+    utils::{check_schema, get_compressor, get_lock, write_all_vectored, CheckedFileName},
+    Binary, Encoder, FrameStream, Json, OutOfOrder, Raw, SerdeFmt, StoreOptions, TimeSlot,
+};
+#[cfg(feature = "parquet")]
+use crate::{
+    columnar::{ParquetFile, ROW_GROUP_SIZE},
+    Parquet,
+};
+#[cfg(feature = "csv")]
+use crate::{formats::write_csv, Csv};
+#[cfg(feature = "parquet")]
+use serde::de::DeserializeOwned;
+
+impl<Kind, Preset, Tz: TimeZone> Drop for Slotted<Kind, Preset, Tz> {
+    fn drop(&mut self) {
+        #[cfg(feature = "parquet")]
+        if let Some(mut file) = self.columnar.take() {
+            if let Err(e) = file.finish() {
+                log::warn!("Error closing {:?}: {e:?}", file.path());
+            }
+        }
+        if let Some(tx) = &self.compress_tx {
+            if let Some(hdl) = self.compress_hdl.take() {
+                let _ = tx.send(None);
+                let _ = hdl.join();
+            }
+        }
+    }
+}
+
+/// Data storage configuration of some type, such as [Binary], [Json],
+/// [Raw], or any user-defined type implementing [Encoder], with files
+/// covering time slots of a configurable [SlotLength] in time zone `Tz`.
+///
+/// This struct is used to store data frames in a directory
+/// structure relative to the root as follows:
+/// /path/to/root/GROUP/YYYYMMDDHHMMSS.{EXTENSION}
+///
+/// where the file name is the UTC start of the time slot of the file,
+/// and the directory GROUP is chosen by the [Grouping] of the store.
+/// Data frames are appended to the file of their time slot.
+///
+/// Stores created in a time zone using `new_in` split files at the wall
+/// clock boundaries of that time zone, e.g. at local midnight, so days
+/// may last 23 or 25 hours when clocks are changed. Their file names
+/// contain the local start of the time slot followed by its UTC offset,
+/// /path/to/root/GROUP/YYYYMMDDHHMMSS+HHMM.{EXTENSION}, which keeps them
+/// unambiguous when a local hour occurs twice. Directories are named
+/// after the local date.
+///
+/// The layout can be replaced by a [PathTemplate], set using
+/// [StoreOptions::path_template], e.g. /path/to/root/YYYY/MM/DD/<station>_YYYYMMDDTHH.{EXTENSION}.
+/// The files of the store are found using [Slotted::discover].
+///
+/// If compression is enabled, a directory is compressed into a
+/// tarball once the store moves on to the next directory, as
+/// /path/to/root/GROUP.tar.gz. The directory GROUP is removed after
+/// successful compression. Compression is deferred by the grace period
+/// of the store ([StoreOptions::grace_period]).
+///
+/// Data frames older than the time slot of the latest frame stored are
+/// handled according to [StoreOptions::out_of_order].
+///
+/// [UtcHourly](crate::UtcHourly) and [UtcDaily](crate::UtcDaily) are presets of this store.
+///
+/// Usage:
+/// ```rust,no_run
+/// use datastor::{UtcSlotted, Binary, Grouping, SlotLength};
+/// use chrono::{Utc, Duration};
+/// // 10 minute files, grouped by day
+/// let mut store = UtcSlotted::<Binary>::new(
+///     "test".into(),
+///     true,
+///     "testprogram",
+///     SlotLength::Duration(Duration::minutes(10)),
+///     Grouping::Day,
+/// )
+/// .unwrap();
+/// let data = vec![1, 2, 3, 4, 5];
+/// let now = Utc::now();
+/// let _ = store.store(now, data.as_ref()).unwrap(); // first frame
+/// let _ = store.store(now + Duration::minutes(20), data.as_ref()).unwrap(); // second frame, in a new file
+/// ```
+///
+/// Files split at local midnight:
+/// ```rust,no_run
+/// use datastor::{Binary, StoreOptions, ZonedDaily};
+/// use chrono::{Local, Utc};
+/// let mut store =
+///     ZonedDaily::<Binary, Local>::new_in("test".into(), true, "testprogram", Local).unwrap();
+/// let _ = store.store(Utc::now(), &[1, 2, 3]).unwrap();
+/// ```
+pub struct Slotted<Kind, Preset = CustomSlots, Tz: TimeZone = Utc> {
+    root_dir: PathBuf,
+    current_dir: Option<PathBuf>,
+    slot: SlotLength,
+    grouping: Grouping,
+    template: PathTemplate,
+    exec: u64,
+    seq: u64,
+    next_seq: u64,
+    head: Option<(DateTime<Utc>, u64)>,
+    slot_start: Option<DateTime<Utc>>,
+    latest: Option<DateTime<Utc>>,
+    tz: Tz,
+    name: &'static str,
+    compress_tx: Option<mpsc::Sender<Option<PathBuf>>>,
+    compress_hdl: Option<thread::JoinHandle<()>>,
+    archive: ArchiveQueue,
+    writer: Option<File>,
+    current_file: Option<PathBuf>,
+    index: Option<IndexWriter>,
+    progname: &'static str,
+    options: StoreOptions,
+    #[cfg(feature = "parquet")]
+    columnar: Option<ParquetFile>,
+    _lock: LockFile,
+    _marker: PhantomData<(Kind, Preset)>,
+}
+
+/// [Slotted] store with UTC time slots.
+pub type UtcSlotted<Kind, Preset = CustomSlots> = Slotted<Kind, Preset, Utc>;
+
+/// [Slotted] store of minute files with UTC time slots, grouped into daily directories.
+pub type UtcMinutely<Kind> = Slotted<Kind, MinutelySlots>;
+
+/// [Slotted] store of minute files in time zone `Tz`, grouped into daily directories.
+pub type ZonedMinutely<Kind, Tz> = Slotted<Kind, MinutelySlots, Tz>;
+
+/// [Slotted] store of hourly files in time zone `Tz`, grouped into daily directories.
+pub type ZonedHourly<Kind, Tz> = Slotted<Kind, HourlySlots, Tz>;
+
+/// [Slotted] store of daily files in time zone `Tz`, grouped into daily directories.
+pub type ZonedDaily<Kind, Tz> = Slotted<Kind, DailySlots, Tz>;
+
+impl<Kind: FmtInfo> Slotted<Kind> {
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the directories of files will be compressed into tarballs.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `slot`: Length of the time slot covered by each file.
+    /// - `grouping`: Grouping of the files into directories.
+    ///
+    /// # Errors:
+    /// - If the slot length is not positive, an `std::io::Error` with `InvalidInput` kind is returned.
+    /// - If the root directory cannot be created, or the store is already in use.
+    pub fn new(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+        slot: SlotLength,
+        grouping: Grouping,
+    ) -> Result<Self, std::io::Error> {
+        Self::new_with_options(
+            root_dir,
+            compress,
+            progname,
+            slot,
+            grouping,
+            StoreOptions::default(),
+        )
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the directories of files will be compressed into tarballs.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `slot`: Length of the time slot covered by each file.
+    /// - `grouping`: Grouping of the files into directories.
+    /// - `options`: Options applied to the files created by this store.
+    ///
+    /// # Errors:
+    /// - If the slot length is not positive, an `std::io::Error` with `InvalidInput` kind is returned.
+    /// - If the root directory cannot be created, or the store is already in use.
+    pub fn new_with_options(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+        slot: SlotLength,
+        grouping: Grouping,
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
+        let slot = slot.validate()?;
+        let layout = (slot, grouping, Utc, false, "UtcSlotted");
+        Self::create(root_dir, compress, progname, layout, options)
+    }
+}
+
+impl<Kind: FmtInfo, Preset: SlotPreset> Slotted<Kind, Preset> {
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the directories of files will be compressed into tarballs.
+    /// - `progname`: Name of the program creating this data file.
+    pub fn new(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+    ) -> Result<Self, std::io::Error> {
+        Self::new_with_options(root_dir, compress, progname, StoreOptions::default())
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the directories of files will be compressed into tarballs.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `options`: Options applied to the files created by this store.
+    pub fn new_with_options(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
+        let layout = (Preset::SLOT, Preset::GROUPING, Utc, false, Preset::NAME);
+        Self::create(root_dir, compress, progname, layout, options)
+    }
+}
+
+impl<Kind: FmtInfo, Tz: TimeZone> Slotted<Kind, CustomSlots, Tz> {
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with time slots in time zone `tz`.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the directories of files will be compressed into tarballs.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `slot`: Length of the time slot covered by each file.
+    /// - `grouping`: Grouping of the files into directories.
+    /// - `tz`: Time zone of the time slot boundaries.
+    ///
+    /// # Errors:
+    /// - If the slot length is not positive, an `std::io::Error` with `InvalidInput` kind is returned.
+    /// - If the root directory cannot be created, or the store is already in use.
+    pub fn new_in(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+        slot: SlotLength,
+        grouping: Grouping,
+        tz: Tz,
+    ) -> Result<Self, std::io::Error> {
+        Self::new_in_with_options(
+            root_dir,
+            compress,
+            progname,
+            slot,
+            grouping,
+            tz,
+            StoreOptions::default(),
+        )
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with time slots in time zone `tz`, and the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the directories of files will be compressed into tarballs.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `slot`: Length of the time slot covered by each file.
+    /// - `grouping`: Grouping of the files into directories.
+    /// - `tz`: Time zone of the time slot boundaries.
+    /// - `options`: Options applied to the files created by this store.
+    ///
+    /// # Errors:
+    /// - If the slot length is not positive, an `std::io::Error` with `InvalidInput` kind is returned.
+    /// - If the root directory cannot be created, or the store is already in use.
+    pub fn new_in_with_options(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+        slot: SlotLength,
+        grouping: Grouping,
+        tz: Tz,
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
+        let slot = slot.validate()?;
+        let layout = (slot, grouping, tz, true, "Slotted");
+        Self::create(root_dir, compress, progname, layout, options)
+    }
+}
+
+impl<Kind: FmtInfo, Preset: SlotPreset, Tz: TimeZone> Slotted<Kind, Preset, Tz> {
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with time slots in time zone `tz`.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the directories of files will be compressed into tarballs.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `tz`: Time zone of the time slot boundaries.
+    pub fn new_in(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+        tz: Tz,
+    ) -> Result<Self, std::io::Error> {
+        Self::new_in_with_options(root_dir, compress, progname, tz, StoreOptions::default())
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with time slots in time zone `tz`, and the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the directories of files will be compressed into tarballs.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `tz`: Time zone of the time slot boundaries.
+    /// - `options`: Options applied to the files created by this store.
+    pub fn new_in_with_options(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+        tz: Tz,
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
+        let layout = (Preset::SLOT, Preset::GROUPING, tz, true, Preset::ZONED_NAME);
+        Self::create(root_dir, compress, progname, layout, options)
+    }
+}
+
+impl<Kind: FmtInfo, Preset, Tz: TimeZone> Slotted<Kind, Preset, Tz> {
+    fn create(
+        root_dir: PathBuf,
+        compress: bool,
+        progname: &'static str,
+        (slot, grouping, tz, zoned, name): (SlotLength, Grouping, Tz, bool, &'static str),
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
+        std::fs::create_dir_all(&root_dir)?;
+        let lock = get_lock(&root_dir, options.lock_hash::<Kind>())?;
+        let template = match &options.template {
+            Some(template) => template.clone(),
+            None => PathTemplate::slotted(grouping, zoned),
+        };
+        let (exec, next_seq) = counters(&template, &root_dir, Kind::extension())?;
+        lazy_static! {
+            static ref COMPRESSION_THREAD_TX: Arc<Mutex<Option<mpsc::Sender<Option<PathBuf>>>>> =
+                Arc::new(Mutex::new(None));
+        }
+        // handle compression
+        let (compress_tx, compress_hdl) =
+            get_compressor(compress, (*COMPRESSION_THREAD_TX).clone());
+        let mut archive = ArchiveQueue::new(compress_tx.clone(), &options);
+        if archive.enabled() {
+            // directories left uncompressed by earlier executions
+            for file in template.discover(&root_dir, Kind::extension())? {
+                let (Some(dir), Some(start)) = (file.path.parent(), file.start()) else {
+                    continue;
+                };
+                if !file.archived && dir != root_dir {
+                    let end = slot.end(&slot.start(&start.with_timezone(&tz)));
+                    archive.push(dir.to_path_buf(), end.to_utc());
+                }
+            }
+        }
+        Ok(Self {
+            root_dir,
+            current_dir: None,
+            slot,
+            grouping,
+            template,
+            exec,
+            seq: next_seq,
+            next_seq,
+            head: None,
+            slot_start: None,
+            latest: None,
+            tz,
+            name,
+            writer: None,
+            current_file: None,
+            index: None,
+            archive,
+            compress_tx,
+            compress_hdl,
+            progname,
+            options,
+            #[cfg(feature = "parquet")]
+            columnar: None,
+            _lock: lock,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Length of the time slot covered by each file.
+    pub fn slot_length(&self) -> SlotLength {
+        self.slot
+    }
+
+    /// Grouping of the files into directories, unless the store has a [PathTemplate].
+    pub fn grouping(&self) -> Grouping {
+        self.grouping
+    }
+
+    /// Template of the paths of the files of the store.
+    pub fn path_template(&self) -> &PathTemplate {
+        &self.template
+    }
+
+    #[must_use = "Errors must be handled."]
+    /// Find the data files and tarballs of the store, including those written by
+    /// earlier executions.
+    ///
+    /// # Output:
+    /// - Returns the files matching the [PathTemplate] of the store, sorted by the
+    ///   start of their time slot.
+    ///
+    /// # Errors:
+    /// - If the root directory cannot be read.
+    pub fn discover(&self) -> Result<Vec<DiscoveredFile>, std::io::Error> {
+        self.template.discover(&self.root_dir, Kind::extension())
+    }
+
+    /// Time zone of the time slot boundaries.
+    pub fn timezone(&self) -> &Tz {
+        &self.tz
+    }
+
+    /// Time slot containing `tstamp`.
+    fn time_slot(&self, tstamp: DateTime<Utc>) -> TimeSlot {
+        self.slot.slot(&tstamp.with_timezone(&self.tz))
+    }
+
+    /// Apply the [OutOfOrder] policy of the store to a frame at `tstamp`.
+    ///
+    /// The latest timestamp of the store is only updated once the frame is
+    /// written, see [Slotted::register].
+    ///
+    /// # Returns:
+    /// - The timestamp routing the frame to its file.
+    fn admit(&self, tstamp: DateTime<Utc>) -> Result<DateTime<Utc>, std::io::Error> {
+        let Some(latest) = self.latest else {
+            return Ok(tstamp);
+        };
+        let current = self.slot.start(&latest.with_timezone(&self.tz)).to_utc();
+        if tstamp >= current {
+            return Ok(tstamp);
+        }
+        match self.options.out_of_order {
+            OutOfOrder::Backfill => Ok(tstamp),
+            OutOfOrder::Clamp => Ok(current),
+            OutOfOrder::Reject => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Frame at {tstamp} is older than the current time slot, starting at {current}"
+                ),
+            )),
+        }
+    }
+
+    /// Register a frame at `tstamp` once it has been written, moving the latest
+    /// timestamp of the store forward and releasing the directories whose grace
+    /// period has passed.
+    fn register(&mut self, tstamp: DateTime<Utc>) {
+        let end = self.slot_end();
+        register_latest(&mut self.latest, &mut self.archive, tstamp, end);
+    }
+
+    /// End of the time slot of the current file, if any.
+    fn slot_end(&self) -> Option<DateTime<Utc>> {
+        self.slot_start
+            .map(|start| self.slot.end(&start.with_timezone(&self.tz)).to_utc())
+    }
+
+    /// Sequence number of the file of the time slot starting at `start`.
+    ///
+    /// The sequence number is only incremented when the store moves forward to
+    /// a new time slot. A backfilled time slot reuses the sequence number of its
+    /// file, if found in the root directory, and takes the next one otherwise.
+    fn slot_seq<F: FmtInfo>(
+        &mut self,
+        start: &DateTime<FixedOffset>,
+    ) -> Result<u64, std::io::Error> {
+        match self.head {
+            Some((head, seq)) if head == start.to_utc() => return Ok(seq),
+            Some((head, _)) if head > start.to_utc() => {
+                if let Some(seq) = self.discover_seq::<F>(start)? {
+                    return Ok(seq);
+                }
+            }
+            _ => self.head = Some((start.to_utc(), self.next_seq)),
+        }
+        self.next_seq += 1;
+        Ok(self.next_seq - 1)
+    }
+
+    /// Sequence number of an uncompressed file of the time slot starting at
+    /// `start`, and of the current execution count, found in the root directory.
+    fn discover_seq<F: FmtInfo>(
+        &self,
+        start: &DateTime<FixedOffset>,
+    ) -> Result<Option<u64>, std::io::Error> {
+        if !self.template.has_seq() {
+            return Ok(None);
+        }
+        let seq = self
+            .template
+            .discover(&self.root_dir, F::extension())?
+            .into_iter()
+            .filter(|file| !file.archived && file.local == Some(start.naive_local()))
+            .filter(|file| file.offset.is_none_or(|offset| offset == *start.offset()))
+            .filter(|file| !self.template.has_exec() || file.exec == Some(self.exec))
+            .find_map(|file| file.seq);
+        Ok(seq)
+    }
+
+    /// Find the file of the time slot containing `tstamp`, moving on to its directory.
+    fn check_time<F: FmtInfo>(
+        &mut self,
+        tstamp: DateTime<Utc>,
+    ) -> Result<CheckedFileName, std::io::Error> {
+        let tstamp = self.admit(tstamp)?;
+        let start = self.slot.start(&tstamp.with_timezone(&self.tz));
+        let previous = self.slot_start.replace(start.to_utc());
+        if previous != Some(start.to_utc()) {
+            self.seq = self.slot_seq::<F>(&start.fixed_offset())?;
+        }
+        let mut filename = self
+            .root_dir
+            .join(
+                self.template
+                    .render(&start.fixed_offset(), self.exec, self.seq),
+            )
+            .into_os_string();
+        filename.push(format!(".{}", F::extension()));
+        let filename = PathBuf::from(filename);
+        let dir = filename.parent().unwrap_or(&self.root_dir).to_path_buf();
+        if self.current_dir.as_ref() != Some(&dir) {
+            if let (Some(last_dir), Some(previous)) = (self.current_dir.take(), previous) {
+                if last_dir != self.root_dir {
+                    // Queue the last directory for compression
+                    let end = self.slot.end(&previous.with_timezone(&self.tz));
+                    self.archive.push(last_dir, end.to_utc());
+                }
+            }
+            // If the directory has changed, create a new directory
+            self.archive.reopen(&dir)?;
+            std::fs::create_dir_all(&dir)?;
+            self.current_dir = Some(dir);
+        }
+        if filename.exists() {
+            Ok(CheckedFileName::Old(filename))
+        } else {
+            Ok(CheckedFileName::New(filename))
+        }
+    }
+
+    fn get_writer_checked(
+        &mut self,
+        filename: &CheckedFileName,
+        tstamp: DateTime<Utc>,
+    ) -> Result<&mut File, std::io::Error> {
+        if !filename.exists()
+            || self.writer.is_none()
+            || self.current_file.as_ref() != Some(filename.get_filename())
+        {
+            let slot = self.time_slot(self.slot_start.unwrap_or(tstamp));
+            let header = self
+                .options
+                .file_header::<Kind>(self.progname, self.name, slot);
+            if filename.exists() {
+                check_schema(filename.get_filename(), &header)?;
+            }
+            let writer = filename
+                .clone()
+                .get_writer_with_init(Kind::initialize_with, &header)?;
+            self.writer = Some(writer);
+            self.current_file = Some(filename.get_filename().clone());
+            self.index = match (self.options.index && Kind::framed(), filename.exists()) {
+                (false, _) => None,
+                (true, true) => Some(IndexWriter::open(filename.get_filename())?),
+                (true, false) => Some(IndexWriter::create(filename.get_filename())?),
+            };
+        }
+        Ok(self.writer.as_mut().unwrap())
+    }
+}
+
+/// Move `latest` forward to a frame written at `tstamp`, and release the directories
+/// of `archive` whose grace period has passed, given the `end` of the current time slot.
+fn register_latest(
+    latest: &mut Option<DateTime<Utc>>,
+    archive: &mut ArchiveQueue,
+    tstamp: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+) {
+    let latest = *latest.insert(latest.map_or(tstamp, |latest| latest.max(tstamp)));
+    if let Some(end) = end {
+        archive.release(latest, end);
+    }
+}
+
+/// Execution count and first sequence number of a store using `template`.
+///
+/// The execution count is one more than the largest found in `root_dir`. The
+/// sequence number continues from the largest found, unless the store has a new
+/// execution count.
+fn counters(
+    template: &PathTemplate,
+    root_dir: &std::path::Path,
+    extension: &str,
+) -> Result<(u64, u64), std::io::Error> {
+    template.validate()?;
+    if !template.has_exec() && !template.has_seq() {
+        return Ok((0, 0));
+    }
+    let files = template.discover(root_dir, extension)?;
+    let next = |value: fn(&DiscoveredFile) -> Option<u64>| {
+        files
+            .iter()
+            .filter_map(value)
+            .max()
+            .map_or(0, |max| max + 1)
+    };
+    if template.has_exec() {
+        Ok((next(|file| file.exec).max(1), 0))
+    } else {
+        Ok((0, next(|file| file.seq)))
+    }
+}
+
+impl<Kind: FmtInfo, Preset, Tz: TimeZone> BatchStore for Slotted<Kind, Preset, Tz> {
+    type Tz = Tz;
+
+    fn slots(&self) -> (SlotLength, Tz) {
+        (self.slot, self.tz.clone())
+    }
+
+    fn route(&mut self, tstamp: DateTime<Utc>) -> Result<(PathBuf, &mut File), std::io::Error> {
+        let filename = self.check_time::<Kind>(tstamp)?;
+        let path = filename.get_filename().clone();
+        let writer = self.get_writer_checked(&filename, tstamp)?;
+        Ok((path, writer))
+    }
+
+    fn commit_frames(&mut self, frames: &[(u64, DateTime<Utc>)]) -> Result<(), std::io::Error> {
+        if let Some(tstamp) = frames.iter().map(|(_, tstamp)| *tstamp).max() {
+            self.register(tstamp);
+        }
+        match (self.index.as_mut(), self.writer.as_mut()) {
+            (Some(index), Some(writer)) => index.append(writer, frames),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<Kind: Encoder, Preset, Tz: TimeZone> Slotted<Kind, Preset, Tz> {
+    #[must_use = "Errors must be handled."]
+    /// Store a data frame.
+    ///
+    /// # Arguments:
+    /// - `tstamp`: Timestamp of the data frame, used to determine time slot boundaries.
+    /// - `data`: Data to be stored.
+    ///
+    /// # Output:
+    /// - Returns the path to the file where the data was stored.
+    ///
+    /// # Errors:
+    /// - If the data cannot be encoded, e.g. if it cannot be serialized, or it is too large
+    ///   for the binary format version (4 GiB for version 1.0).
+    /// - If the file cannot be opened or written to.
+    /// - If the file cannot be flushed.
+    pub fn store(
+        &mut self,
+        tstamp: DateTime<Utc>,
+        data: &Kind::Frame,
+    ) -> Result<PathBuf, std::io::Error> {
+        let filename = self.check_time::<Kind>(tstamp)?;
+        let first = self
+            .get_writer_checked(&filename, tstamp)?
+            .metadata()?
+            .len()
+            == 0;
+        let mut repr = Vec::new();
+        if first {
+            Kind::encode_first(&mut repr, data, &self.options)?;
+        } else {
+            Kind::encode(&mut repr, data, &self.options)?;
+        }
+        let writer = self.writer.as_mut().unwrap(); // opened above
+        writer.write_all(&repr)?;
+        writer.flush()?;
+        self.commit_frames(&[(repr.len() as u64, tstamp)])?;
+        Ok(filename.into())
+    }
+}
+
+impl<T: Serialize, Preset, Tz: TimeZone> Slotted<Json<T>, Preset, Tz> {
+    #[must_use = "Errors must be handled."]
+    /// Store a batch of JSON-serializable data frames.
+    ///
+    /// Each frame is routed to the file of its time slot. Consecutive frames
+    /// belonging to the same file are written with a single flush.
+    ///
+    /// # Arguments:
+    /// - `frames`: Timestamps and data of the frames to be stored.
+    /// - `sync`: Whether each file is synced to disk after its frames are written.
+    ///
+    /// # Output:
+    /// - Returns a summary of the files written to.
+    ///
+    /// # Errors:
+    /// - If any of the data cannot be serialized to JSON.
+    /// - If a file cannot be opened, written to, flushed or synced.
+    ///
+    /// Frames preceding the failing frame may have been stored.
+    pub fn store_batch<I, D>(
+        &mut self,
+        frames: I,
+        sync: bool,
+    ) -> Result<BatchSummary, std::io::Error>
+    where
+        I: IntoIterator<Item = (DateTime<Utc>, D)>,
+        D: Borrow<T>,
+    {
+        self.store_batch_with(frames, sync, |writer, data, _| {
+            serde_json::to_writer(writer.by_ref(), data.borrow())
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+            writer.write_all(Json::<T>::delimiter())
+        })
+    }
+}
+
+#[cfg(feature = "csv")]
+impl<T: Serialize, Preset, Tz: TimeZone> Slotted<Csv<T>, Preset, Tz> {
+    #[must_use = "Errors must be handled."]
+    /// Store a batch of data frames as CSV rows.
+    ///
+    /// Each frame is routed to the file of its time slot. Consecutive frames
+    /// belonging to the same file are written with a single flush.
+    ///
+    /// # Arguments:
+    /// - `frames`: Timestamps and data of the frames to be stored.
+    /// - `sync`: Whether each file is synced to disk after its frames are written.
+    ///
+    /// # Output:
+    /// - Returns a summary of the files written to.
+    ///
+    /// # Errors:
+    /// - If any of the data cannot be serialized to CSV.
+    /// - If a file cannot be opened, written to, flushed or synced.
+    ///
+    /// Frames preceding the failing frame may have been stored.
+    pub fn store_batch<I, D>(
+        &mut self,
+        frames: I,
+        sync: bool,
+    ) -> Result<BatchSummary, std::io::Error>
+    where
+        I: IntoIterator<Item = (DateTime<Utc>, D)>,
+        D: Borrow<T>,
+    {
+        self.store_batch_with(frames, sync, |writer, data, first| {
+            write_csv(writer, data.borrow(), first)
+        })
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl<T: Serialize + DeserializeOwned, Preset, Tz: TimeZone> Slotted<Parquet<T>, Preset, Tz> {
+    #[must_use = "Errors must be handled."]
+    /// Store a data frame as a Parquet row.
+    ///
+    /// The row is written to the file once its row group is complete, or the file is closed.
+    /// The file is closed when a data frame belonging to a different time slot is
+    /// stored, when [UtcSlotted::close_file] is called, or when the store is dropped.
+    ///
+    /// # Arguments:
+    /// - `tstamp`: Timestamp of the data frame, used to determine time slot boundaries.
+    /// - `data`: Data to be stored.
+    ///
+    /// # Output:
+    /// - Returns the path to the file where the data will be stored.
+    ///
+    /// # Errors:
+    /// - If the columns cannot be derived from `T`, or the data cannot be serialized.
+    /// - If the previous file cannot be closed.
+    /// - If the file cannot be created or written to.
+    pub fn store(&mut self, tstamp: DateTime<Utc>, data: &T) -> Result<PathBuf, std::io::Error> {
+        let tstamp = self.admit(tstamp)?;
+        let key = self
+            .slot
+            .start(&tstamp.with_timezone(&self.tz))
+            .to_rfc3339();
+        if self.columnar.as_ref().is_some_and(|file| file.key() != key) {
+            // close the file before its directory is compressed
+            self.close_file()?;
+        }
+        let filename = self.check_time::<Parquet<T>>(tstamp)?;
+        let file = match self.columnar.take() {
+            Some(file) => file,
+            None => {
+                let header = self.options.file_header::<Parquet<T>>(
+                    self.progname,
+                    self.name,
+                    self.time_slot(tstamp),
+                );
+                ParquetFile::create::<T>(
+                    filename.get_filename().clone(),
+                    key,
+                    &header,
+                    self.options.row_group_size.unwrap_or(ROW_GROUP_SIZE),
+                )?
+            }
+        };
+        let file = self.columnar.insert(file);
+        file.push(data)?;
+        self.register(tstamp);
+        Ok(filename.into())
+    }
+
+    #[must_use = "Errors must be handled."]
+    /// Write the pending rows and close the current file.
+    ///
+    /// # Output:
+    /// - Returns the path of the closed file, if a file was open.
+    ///
+    /// # Errors:
+    /// - If the file cannot be written to, synced or moved into place. The file is
+    ///   kept open in this case, and closing it is retried by the next call.
+    pub fn close_file(&mut self) -> Result<Option<PathBuf>, std::io::Error> {
+        let Some(file) = self.columnar.as_mut() else {
+            return Ok(None);
+        };
+        let path = file.finish()?;
+        self.columnar = None;
+        Ok(Some(path))
+    }
+}
+
+impl<Kind: SerdeFmt, Preset, Tz: TimeZone> Slotted<Kind, Preset, Tz> {
+    #[must_use = "Errors must be handled."]
+    /// Store a batch of serialized data frames.
+    ///
+    /// Each frame is routed to the file of its time slot. Consecutive frames
+    /// belonging to the same file are written with a single flush.
+    ///
+    /// # Arguments:
+    /// - `frames`: Timestamps and data of the frames to be stored.
+    /// - `sync`: Whether each file is synced to disk after its frames are written.
+    ///
+    /// # Output:
+    /// - Returns a summary of the files written to.
+    ///
+    /// # Errors:
+    /// - If any of the data cannot be serialized.
+    /// - If a file cannot be opened, written to, flushed or synced.
+    ///
+    /// Frames preceding the failing frame may have been stored.
+    pub fn store_batch<I, D>(
+        &mut self,
+        frames: I,
+        sync: bool,
+    ) -> Result<BatchSummary, std::io::Error>
+    where
+        I: IntoIterator<Item = (DateTime<Utc>, D)>,
+        D: Borrow<Kind::Frame>,
+    {
+        let (version, codec) = (self.options.binary_version, self.options.codec);
+        self.store_batch_with(frames, sync, |writer, data, _| {
+            write_frame(writer, &Kind::to_bytes(data.borrow())?, version, codec)
+        })
+    }
+}
+
+impl<Preset, Tz: TimeZone> Slotted<Binary, Preset, Tz> {
+    #[must_use = "Errors must be handled."]
+    /// Store a binary data frame gathered from multiple buffers.
+    ///
+    /// The frame size is computed across all buffers, and the buffers are
+    /// written using vectored writes without being concatenated first.
+    ///
+    /// # Arguments:
+    /// - `tstamp`: Timestamp of the data frame, used to determine time slot boundaries.
+    /// - `data`: Buffers that make up the data to be stored.
+    ///
+    /// # Output:
+    /// - Returns the path to the file where the data was stored.
+    ///
+    /// # Errors:
+    /// - If the data is too large for the binary format version (4 GiB for version 1.0).
+    /// - If the file cannot be opened or written to.
+    /// - If the file cannot be flushed.
+    pub fn store_vectored(
+        &mut self,
+        tstamp: DateTime<Utc>,
+        data: &[IoSlice<'_>],
+    ) -> Result<PathBuf, std::io::Error> {
+        let filename = self.check_time::<Binary>(tstamp)?;
+        let (version, codec) = (self.options.binary_version, self.options.codec);
+        let writer = self.get_writer_checked(&filename, tstamp)?;
+        let len = store_binary_vectored(writer, data, version, codec)?;
+        self.commit_frames(&[(len, tstamp)])?;
+        Ok(filename.into())
+    }
+
+    #[must_use = "Errors must be handled."]
+    /// Store a batch of binary data frames.
+    ///
+    /// Each frame is routed to the file of its time slot. Consecutive frames
+    /// belonging to the same file are written with a single flush.
+    ///
+    /// # Arguments:
+    /// - `frames`: Timestamps and data of the frames to be stored.
+    /// - `sync`: Whether each file is synced to disk after its frames are written.
+    ///
+    /// # Output:
+    /// - Returns a summary of the files written to.
+    ///
+    /// # Errors:
+    /// - If any of the data is too large for the binary format version (4 GiB for version 1.0).
+    /// - If a file cannot be opened, written to, flushed or synced.
+    ///
+    /// Frames preceding the failing frame may have been stored.
+    pub fn store_batch<I, D>(
+        &mut self,
+        frames: I,
+        sync: bool,
+    ) -> Result<BatchSummary, std::io::Error>
+    where
+        I: IntoIterator<Item = (DateTime<Utc>, D)>,
+        D: AsRef<[u8]>,
+    {
+        let (version, codec) = (self.options.binary_version, self.options.codec);
+        self.store_batch_with(frames, sync, |writer, data, _| {
+            write_frame(writer, data.as_ref(), version, codec)
+        })
+    }
+
+    #[must_use = "Errors must be handled."]
+    /// Stream a binary data frame of unknown size.
+    ///
+    /// # Arguments:
+    /// - `tstamp`: Timestamp of the data frame, used to determine time slot boundaries.
+    ///
+    /// # Output:
+    /// - Returns a [FrameStream] to write the frame payload into. The frame is stored once
+    ///   [FrameStream::finish] is called, and discarded if the stream is dropped.
+    ///
+    /// # Errors:
+    /// - If the file cannot be opened or written to.
+    pub fn store_stream(
+        &mut self,
+        tstamp: DateTime<Utc>,
+    ) -> Result<FrameStream<'_>, std::io::Error> {
+        let filename = self.check_time::<Binary>(tstamp)?;
+        let (version, codec) = (self.options.binary_version, self.options.codec);
+        self.get_writer_checked(&filename, tstamp)?;
+        let end = self.slot_end();
+        let (latest, archive) = (&mut self.latest, &mut self.archive);
+        let writer = self.writer.as_mut().expect("Writer is opened above");
+        Ok(FrameStream::new(writer, Framing::Binary(version, codec))?
+            .indexed(self.index.as_mut(), tstamp)
+            .on_finish(Box::new(move || {
+                register_latest(latest, archive, tstamp, end)
+            })))
+    }
+}
+
+impl<Preset, Tz: TimeZone> Slotted<Raw, Preset, Tz> {
+    #[must_use = "Errors must be handled"]
+    /// Store data gathered from multiple buffers without any delimiters.
+    ///
+    /// # Arguments:
+    /// - `tstamp`: Timestamp of the data frame, used to determine time slot boundaries.
+    /// - `data`: Buffers that make up the data to be stored.
+    ///
+    /// # Output:
+    /// - Returns the path to the file where the data was stored.
+    ///
+    /// # Errors:
+    /// - If the file cannot be opened or written to.
+    /// - If the file cannot be flushed.
+    pub fn store_vectored(
+        &mut self,
+        tstamp: DateTime<Utc>,
+        data: &[IoSlice<'_>],
+    ) -> Result<PathBuf, std::io::Error> {
+        let filename = self.check_time::<Raw>(tstamp)?;
+        let writer = self.get_writer_checked(&filename, tstamp)?;
+        write_all_vectored(writer, &mut data.to_vec())?;
+        writer.flush()?;
+        self.register(tstamp);
+        Ok(filename.into())
+    }
+
+    #[must_use = "Errors must be handled"]
+    /// Store a batch of data without any delimiters.
+    ///
+    /// Each frame is routed to the file of its time slot. Consecutive frames
+    /// belonging to the same file are written with a single flush.
+    ///
+    /// # Arguments:
+    /// - `frames`: Timestamps and data of the frames to be stored.
+    /// - `sync`: Whether each file is synced to disk after its frames are written.
+    ///
+    /// # Output:
+    /// - Returns a summary of the files written to.
+    ///
+    /// # Errors:
+    /// - If a file cannot be opened, written to, flushed or synced.
+    ///
+    /// Frames preceding the failing frame may have been stored.
+    pub fn store_batch<I, D>(
+        &mut self,
+        frames: I,
+        sync: bool,
+    ) -> Result<BatchSummary, std::io::Error>
+    where
+        I: IntoIterator<Item = (DateTime<Utc>, D)>,
+        D: AsRef<[u8]>,
+    {
+        self.store_batch_with(frames, sync, |writer, data, _| {
+            writer.write_all(data.as_ref())
+        })
+    }
+
+    #[must_use = "Errors must be handled"]
+    /// Stream data of unknown size without any delimiters.
+    ///
+    /// # Arguments:
+    /// - `tstamp`: Timestamp of the data frame, used to determine time slot boundaries.
+    ///
+    /// # Output:
+    /// - Returns a [FrameStream] to write the data into. The data is kept once
+    ///   [FrameStream::finish] is called, and discarded if the stream is dropped.
+    ///
+    /// # Errors:
+    /// - If the file cannot be opened or written to.
+    pub fn store_stream(
+        &mut self,
+        tstamp: DateTime<Utc>,
+    ) -> Result<FrameStream<'_>, std::io::Error> {
+        let filename = self.check_time::<Raw>(tstamp)?;
+        self.get_writer_checked(&filename, tstamp)?;
+        let end = self.slot_end();
+        let (latest, archive) = (&mut self.latest, &mut self.archive);
+        let writer = self.writer.as_mut().expect("Writer is opened above");
+        Ok(
+            FrameStream::new(writer, Framing::Raw)?.on_finish(Box::new(move || {
+                register_latest(latest, archive, tstamp, end)
+            })),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use chrono_tz::Europe::Amsterdam;
+
+    use super::*;
+    use crate::{UtcDaily, UtcHourly};
+
+    #[test]
+    fn test_slots() {
+        let dir = "test_slots_utcslotted";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 12, 34, 56).unwrap();
+        let mut store = UtcSlotted::<Json<u32>>::new(
+            dir.into(),
+            false,
+            "testprogram",
+            SlotLength::Duration(Duration::minutes(10)),
+            Grouping::Flat,
+        )
+        .unwrap();
+        let frames = (0..4).map(|i| (start + Duration::minutes(5 * i), i as u32));
+        let summary = store.store_batch(frames, false).unwrap();
+        let files = summary
+            .files
+            .iter()
+            .map(|(path, count)| (path.to_str().unwrap(), *count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            vec![
+                ("test_slots_utcslotted/20250101123000.json", 2),
+                ("test_slots_utcslotted/20250101124000.json", 2),
+            ]
+        );
+        let header = crate::read_header("test_slots_utcslotted/20250101123000.json").unwrap();
+        assert_eq!(header.store, "UtcSlotted");
+        drop(store);
+
+        let mut store = UtcSlotted::<Json<u32>>::new(
+            dir.into(),
+            false,
+            "testprogram",
+            SlotLength::IsoWeek,
+            Grouping::Month,
+        )
+        .unwrap();
+        let path = store.store(start, &1).unwrap();
+        assert_eq!(
+            path,
+            std::path::Path::new(dir).join("202412/20241230000000.json")
+        );
+        assert_eq!(store.store(start + Duration::days(4), &2).unwrap(), path);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+
+        assert!(UtcSlotted::<Json<u32>>::new(
+            dir.into(),
+            false,
+            "testprogram",
+            SlotLength::Duration(Duration::zero()),
+            Grouping::Flat,
+        )
+        .is_err());
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_batch_encode_error() {
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize, serde::Deserialize)]
+        struct Frame {
+            id: u32,
+            // non-string keys fail to serialize to JSON, after the frame is started
+            map: BTreeMap<Vec<u8>, u32>,
+        }
+        let frame = |id: u32, map: &[(Vec<u8>, u32)]| Frame {
+            id,
+            map: map.iter().cloned().collect(),
+        };
+        let dir = "test_batch_encode_error";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = UtcHourly::<Json<Frame>>::new(dir.into(), false, "testprogram").unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let frames = [
+            (start, frame(0, &[])),
+            (start, frame(1, &[])),
+            (start, frame(2, &[(vec![1], 1)])),
+            (start, frame(3, &[])),
+        ];
+        assert!(store.store_batch(frames, false).is_err());
+        let path = store.store(start, &frame(4, &[])).unwrap();
+        let (_, frames) = crate::JsonReader::<serde_json::Value>::new(0)
+            .read(&path)
+            .unwrap();
+        let ids = frames
+            .iter()
+            .map(|frame| frame["id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [0, 1, 4]);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_batch_latest() {
+        let dir = "test_batch_latest";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = UtcHourly::<Json<u32>>::new(dir.into(), false, "testprogram").unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let frames = [
+            (start + Duration::minutes(10), 0),
+            (start + Duration::minutes(50), 1),
+            (start + Duration::minutes(30), 2),
+        ];
+        store.store_batch(frames, false).unwrap();
+        assert_eq!(store.latest, Some(start + Duration::minutes(50)));
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_minutely() {
+        let dir = "test_minutely_slotted";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = UtcMinutely::<Json<u32>>::new(dir.into(), false, "testprogram").unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 10, 30, 15).unwrap();
+        let path = store.store(start, &1).unwrap();
+        assert_eq!(
+            path,
+            std::path::Path::new(dir).join("20250101/20250101103000.json")
+        );
+        assert_eq!(crate::read_header(&path).unwrap().store, "UtcMinutely");
+        drop(store);
+        let mut store =
+            ZonedMinutely::<Json<u32>, _>::new_in(dir.into(), false, "testprogram", Amsterdam)
+                .unwrap();
+        let path = store.store(start, &2).unwrap();
+        assert_eq!(crate::read_header(&path).unwrap().store, "ZonedMinutely");
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_zoned() {
+        let dir = "test_zoned_slotted";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store =
+            ZonedDaily::<Json<u32>, _>::new_in(dir.into(), false, "testprogram", Amsterdam)
+                .unwrap();
+        // the day clocks are turned back lasts 25 hours
+        let midnight = Utc.with_ymd_and_hms(2025, 10, 25, 22, 0, 0).unwrap();
+        let frames = [-1, 0, 24, 25].map(|hours| (midnight + Duration::hours(hours), hours as u32));
+        let summary = store.store_batch(frames, false).unwrap();
+        let files = summary
+            .files
+            .iter()
+            .map(|(path, count)| (path.to_str().unwrap(), *count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            vec![
+                ("test_zoned_slotted/20251025/20251025000000+0200.json", 1),
+                ("test_zoned_slotted/20251026/20251026000000+0200.json", 2),
+                ("test_zoned_slotted/20251027/20251027000000+0100.json", 1),
+            ]
+        );
+        let header = crate::read_header(files[1].0).unwrap();
+        assert_eq!(header.store, "ZonedDaily");
+        assert_eq!(
+            header.slot,
+            Some(TimeSlot::Utc {
+                start: midnight,
+                end: midnight + Duration::hours(25)
+            })
+        );
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_template() {
+        let dir = "test_template_slotted";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let template = PathTemplate::new("%Y/%m/%d/{station}_%Y%m%dT%H")
+            .unwrap()
+            .token("station", "north");
+        let options = StoreOptions::new().path_template(template);
+        let mut store =
+            UtcHourly::<Json<u32>>::new_with_options(dir.into(), false, "testprogram", options)
+                .unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 12, 31, 23, 30, 0).unwrap();
+        let first = store.store(start, &1).unwrap();
+        let second = store.store(start + Duration::hours(1), &2).unwrap();
+        assert_eq!(
+            first,
+            std::path::Path::new(dir).join("2024/12/31/north_20241231T23.json")
+        );
+        assert_eq!(
+            second,
+            std::path::Path::new(dir).join("2025/01/01/north_20250101T00.json")
+        );
+        let files = store.discover().unwrap();
+        assert_eq!(
+            files.iter().map(|file| &file.path).collect::<Vec<_>>(),
+            vec![&first, &second]
+        );
+        assert_eq!(files[1].start(), Some(start + Duration::minutes(30)));
+        drop(store);
+
+        // a token without value
+        let template = PathTemplate::new("{station}_%Y%m%d").unwrap();
+        let options = StoreOptions::new().path_template(template);
+        assert!(
+            UtcDaily::<Json<u32>>::new_with_options(dir.into(), false, "testprogram", options)
+                .is_err()
+        );
+
+        let template = PathTemplate::new("run{exec}/%Y%m%d_{seq:3}").unwrap();
+        let options = StoreOptions::new().path_template(template);
+        let store = |options| {
+            UtcHourly::<Json<u32>>::new_with_options(dir.into(), false, "testprogram", options)
+                .unwrap()
+        };
+        let mut first = store(options.clone());
+        let path = first.store(start, &1).unwrap();
+        assert_eq!(
+            path,
+            std::path::Path::new(dir).join("run1/20241231_000.json")
+        );
+        let path = first.store(start + Duration::hours(1), &2).unwrap();
+        assert_eq!(
+            path,
+            std::path::Path::new(dir).join("run1/20250101_001.json")
+        );
+        drop(first);
+        let mut second = store(options);
+        let path = second.store(start, &3).unwrap();
+        assert_eq!(
+            path,
+            std::path::Path::new(dir).join("run2/20241231_000.json")
+        );
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let dir = "test_out_of_order_slotted";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let store = |policy| {
+            let options = StoreOptions::new().out_of_order(policy);
+            UtcHourly::<Json<u32>>::new_with_options(dir.into(), false, "testprogram", options)
+                .unwrap()
+        };
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 23, 30, 0).unwrap();
+        let mut backfill = store(OutOfOrder::Backfill);
+        let first = backfill.store(start, &1).unwrap();
+        backfill.store(start + Duration::hours(1), &2).unwrap();
+        assert_eq!(
+            backfill.store(start + Duration::minutes(15), &3).unwrap(),
+            first
+        );
+        drop(backfill);
+        let (_, frames) = crate::JsonReader::<u32>::new(0).read(&first).unwrap();
+        assert_eq!(frames, vec![1, 3]);
+
+        let mut reject = store(OutOfOrder::Reject);
+        reject.store(start, &1).unwrap();
+        reject.store(start - Duration::minutes(15), &2).unwrap(); // same time slot
+        let err = reject.store(start - Duration::hours(1), &3).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        drop(reject);
+
+        let mut clamp = store(OutOfOrder::Clamp);
+        let last = clamp.store(start + Duration::hours(1), &1).unwrap();
+        assert_eq!(clamp.store(start, &2).unwrap(), last);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_out_of_order_failed() {
+        use std::collections::BTreeMap;
+
+        let dir = "test_out_of_order_failed";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let options = StoreOptions::new().out_of_order(OutOfOrder::Reject);
+        let mut store = UtcHourly::<Json<BTreeMap<Vec<u8>, u32>>>::new_with_options(
+            dir.into(),
+            false,
+            "testprogram",
+            options,
+        )
+        .unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 12, 30, 0).unwrap();
+        store.store(start, &BTreeMap::new()).unwrap();
+        // non-string keys fail to serialize to JSON: the frame is not written
+        let failed = BTreeMap::from([(vec![1], 1)]);
+        assert!(store.store(start + Duration::hours(2), &failed).is_err());
+        assert_eq!(store.latest, Some(start));
+        store
+            .store(start - Duration::minutes(15), &BTreeMap::new())
+            .unwrap();
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_backfill_seq() {
+        let dir = "test_backfill_seq_slotted";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let options = StoreOptions::new()
+            .out_of_order(OutOfOrder::Backfill)
+            .path_template(PathTemplate::new("%Y%m%d/%H_{seq}").unwrap());
+        let mut store =
+            UtcHourly::<Json<u32>>::new_with_options(dir.into(), false, "testprogram", options)
+                .unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap();
+        let mut name = |tstamp, value| {
+            let path = store.store(tstamp, &value).unwrap();
+            path.strip_prefix(dir).unwrap().to_str().unwrap().to_owned()
+        };
+        assert_eq!(name(start, 1), "20250101/10_0.json");
+        assert_eq!(name(start + Duration::hours(1), 2), "20250101/11_1.json");
+        // backfilling reuses the file of the time slot
+        assert_eq!(name(start, 3), "20250101/10_0.json");
+        assert_eq!(name(start + Duration::hours(2), 4), "20250101/12_2.json");
+        assert_eq!(name(start + Duration::hours(1), 5), "20250101/11_1.json");
+        // a backfilled time slot without a file takes the next sequence number
+        assert_eq!(name(start - Duration::hours(1), 6), "20250101/09_3.json");
+        assert_eq!(name(start - Duration::hours(1), 7), "20250101/09_3.json");
+        assert_eq!(name(start + Duration::hours(3), 8), "20250101/13_4.json");
+        assert_eq!(name(start + Duration::hours(2), 9), "20250101/12_2.json");
+        drop(store);
+        let path = std::path::Path::new(dir).join("20250101/10_0.json");
+        let (_, frames) = crate::JsonReader::<u32>::new(0).read(&path).unwrap();
+        assert_eq!(frames, vec![1, 3]);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_grace() {
+        let dir = "test_grace_slotted";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let store = || {
+            let options = StoreOptions::new().grace_period(Duration::days(1));
+            UtcHourly::<Json<u32>>::new_with_options(dir.into(), true, "testprogram", options)
+                .unwrap()
+        };
+        let pending = |store: &UtcHourly<Json<u32>>| {
+            let mut dirs = store
+                .archive
+                .pending
+                .iter()
+                .map(|(dir, _)| dir.to_str().unwrap().to_owned())
+                .collect::<Vec<_>>();
+            dirs.sort();
+            dirs
+        };
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap();
+        let mut first = store();
+        first.store(start, &1).unwrap();
+        first.store(start + Duration::days(1), &2).unwrap();
+        assert_eq!(pending(&first), vec!["test_grace_slotted/20250101"]);
+        drop(first);
+
+        // the queue is recovered from the directories
+        let mut second = store();
+        assert_eq!(
+            pending(&second),
+            vec!["test_grace_slotted/20250101", "test_grace_slotted/20250102"]
+        );
+        second.store(start + Duration::days(1), &3).unwrap();
+        assert_eq!(pending(&second), vec!["test_grace_slotted/20250101"]);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+}
@@ -0,0 +1,499 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{
+    format::{parse, Parsed, StrftimeItems},
+    DateTime, FixedOffset, NaiveDateTime, TimeDelta, Utc, Weekday,
+};
+
+use crate::Grouping;
+
+/// Extension of the tarballs of compressed directories.
+const ARCHIVE_EXTENSION: &str = ".tar.gz";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Field of a [PathTemplate].
+enum Field {
+    /// Text copied as is.
+    Literal(String),
+    /// `strftime` specifier, without the leading `%`.
+    Time(char),
+    /// Static token, replaced by its value.
+    Token(String),
+    /// Execution count, zero-padded to the given width.
+    Exec(usize),
+    /// Sequence number, zero-padded to the given width.
+    Seq(usize),
+}
+
+impl Field {
+    /// Number of characters of a `strftime` field.
+    fn time_width(spec: char) -> usize {
+        match spec {
+            'Y' | 'G' => 4,
+            'j' => 3,
+            'z' => 5,
+            _ => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Template of the paths of the data files of a [Slotted](crate::Slotted) store,
+/// relative to its root directory and without the file extension.
+///
+/// Templates are made of the following fields, separated by `/` into directories:
+/// - `strftime` fields of the start of the time slot of the file: `%Y`, `%y`, `%m`,
+///   `%d`, `%j`, `%H`, `%M`, `%S`, `%G`, `%V` and `%z`. `%%` is a literal `%`.
+/// - Static tokens, `{name}`, replaced by the value set using [PathTemplate::token].
+/// - The execution count, `{exec}`, which is one more than the largest execution
+///   count found in the root directory when the store is created.
+/// - The sequence number, `{seq}`, which counts the files of the store, and is
+///   incremented every time the store moves forward to a new time slot. Frames
+///   backfilled into an older time slot go to its existing file, or to a file
+///   with the next sequence number if none is found uncompressed.
+///
+/// The execution count and sequence number are zero-padded to a width given
+/// after a colon, e.g. `{exec:4}`. Unpadded numbers should be followed by a
+/// non-digit, so that paths can be matched against the template.
+///
+/// The directory of a file is compressed once the store moves on to a different
+/// directory, unless the file is stored directly in the root directory. The same
+/// template finds the files and tarballs of a store using [PathTemplate::discover].
+///
+/// Usage:
+/// ```rust,no_run
+/// use datastor::{Binary, PathTemplate, StoreOptions, UtcHourly};
+/// // root/YYYY/MM/DD/<station>_YYYYMMDDTHH.bin
+/// let template = PathTemplate::new("%Y/%m/%d/{station}_%Y%m%dT%H")
+///     .unwrap()
+///     .token("station", "north");
+/// let options = StoreOptions::new().path_template(template);
+/// let mut store =
+///     UtcHourly::<Binary>::new_with_options("test".into(), true, "testprogram", options).unwrap();
+/// ```
+pub struct PathTemplate {
+    source: String,
+    fields: Vec<Field>,
+    tokens: BTreeMap<String, String>,
+}
+
+impl PathTemplate {
+    /// Parse a path template.
+    ///
+    /// # Arguments:
+    /// - `template`: Path template, e.g. `%Y/%m/%d/{station}_%Y%m%dT%H`.
+    ///
+    /// # Errors:
+    /// - If the template is empty, is an absolute path, contains an unsupported
+    ///   `strftime` specifier or an unterminated or invalid `{}` field, an
+    ///   `std::io::Error` with `InvalidInput` kind is returned.
+    pub fn new(template: &str) -> Result<Self, std::io::Error> {
+        let invalid = |msg: String| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid path template {template:?}: {msg}"),
+            )
+        };
+        if template.is_empty() || template.starts_with('/') || template.ends_with('/') {
+            return Err(invalid("expected a relative file path".into()));
+        }
+        let mut fields = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            let field = match c {
+                '%' => match chars.next() {
+                    Some('%') => {
+                        literal.push('%');
+                        continue;
+                    }
+                    Some(
+                        spec @ ('Y' | 'y' | 'm' | 'd' | 'j' | 'H' | 'M' | 'S' | 'G' | 'V' | 'z'),
+                    ) => Field::Time(spec),
+                    spec => return Err(invalid(format!("unsupported specifier {spec:?}"))),
+                },
+                '{' => {
+                    let Some((body, rest)) = chars.as_str().split_once('}') else {
+                        return Err(invalid("unterminated field".into()));
+                    };
+                    let body = body.to_owned();
+                    chars = rest.chars();
+                    let (name, width) = match body.split_once(':') {
+                        Some((name, width)) => (
+                            name,
+                            width
+                                .parse()
+                                .map_err(|_| invalid(format!("invalid width in {{{body}}}")))?,
+                        ),
+                        None => (body.as_str(), 0),
+                    };
+                    match name {
+                        "exec" => Field::Exec(width),
+                        "seq" => Field::Seq(width),
+                        _ if name.is_empty() || name.contains(['{', '/', '%']) => {
+                            return Err(invalid(format!("invalid field {{{body}}}")))
+                        }
+                        _ if body.contains(':') => {
+                            return Err(invalid(format!("tokens have no width: {{{body}}}")))
+                        }
+                        _ => Field::Token(name.to_owned()),
+                    }
+                }
+                c => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+            if !literal.is_empty() {
+                fields.push(Field::Literal(std::mem::take(&mut literal)));
+            }
+            fields.push(field);
+        }
+        if !literal.is_empty() {
+            fields.push(Field::Literal(literal));
+        }
+        Ok(Self {
+            source: template.to_owned(),
+            fields,
+            tokens: BTreeMap::new(),
+        })
+    }
+
+    /// Set the value of a static token of the template.
+    ///
+    /// # Arguments:
+    /// - `name`: Name of the token, `{name}` in the template.
+    /// - `value`: Text replacing the token in paths.
+    pub fn token(mut self, name: &str, value: &str) -> Self {
+        self.tokens.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Template of the default layout of a [Slotted](crate::Slotted) store,
+    /// e.g. `%Y%m%d/%Y%m%d%H%M%S` for files grouped by day.
+    pub(crate) fn slotted(grouping: Grouping, zoned: bool) -> Self {
+        let name = if zoned {
+            "%Y%m%d%H%M%S%z"
+        } else {
+            "%Y%m%d%H%M%S"
+        };
+        let template = match grouping.format() {
+            Some(dir) => format!("{dir}/{name}"),
+            None => name.to_owned(),
+        };
+        Self::new(&template).expect("Default templates are valid")
+    }
+
+    /// Make sure every static token of the template has a value.
+    pub(crate) fn validate(&self) -> Result<(), std::io::Error> {
+        for field in &self.fields {
+            if let Field::Token(name) = field {
+                if !self.tokens.contains_key(name) {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!(
+                            "No value for token {{{name}}} of path template {:?}",
+                            self.source
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the template contains the execution count.
+    pub(crate) fn has_exec(&self) -> bool {
+        self.fields.iter().any(|f| matches!(f, Field::Exec(_)))
+    }
+
+    /// Whether the template contains the sequence number.
+    pub(crate) fn has_seq(&self) -> bool {
+        self.fields.iter().any(|f| matches!(f, Field::Seq(_)))
+    }
+
+    /// Path of a file relative to the root directory, without extension.
+    ///
+    /// # Arguments:
+    /// - `start`: Start of the time slot of the file, in the time zone of the store.
+    /// - `exec`: Execution count of the store.
+    /// - `seq`: Sequence number of the file.
+    pub(crate) fn render(&self, start: &DateTime<FixedOffset>, exec: u64, seq: u64) -> PathBuf {
+        let mut path = String::new();
+        for field in &self.fields {
+            match field {
+                Field::Literal(text) => path.push_str(text),
+                Field::Time(spec) => path.push_str(&start.format(&format!("%{spec}")).to_string()),
+                Field::Token(name) => path.push_str(self.tokens.get(name).map_or("", |v| v)),
+                Field::Exec(width) => path.push_str(&format!("{exec:0>width$}")),
+                Field::Seq(width) => path.push_str(&format!("{seq:0>width$}")),
+            }
+        }
+        PathBuf::from(path)
+    }
+
+    /// Template of the directories of the files, if files are not stored in the root directory.
+    fn parent(&self) -> Option<Self> {
+        let (dir, _) = self.source.rsplit_once('/')?;
+        let template = Self::new(dir).ok()?;
+        Some(Self {
+            tokens: self.tokens.clone(),
+            ..template
+        })
+    }
+
+    /// Find the data files and tarballs matching the template in a root directory.
+    ///
+    /// Tarballs of compressed directories are matched against the directory part
+    /// of the template, and carry the fields found in their name only.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory of the store.
+    /// - `extension`: Extension of the data files, e.g. `bin`.
+    ///
+    /// # Returns:
+    /// - `Ok(Vec<DiscoveredFile>)` with the matching files, sorted by the start of
+    ///   their time slot, execution count, sequence number and path.
+    /// - `Err(std::io::Error)` if there was an error during the process.
+    ///
+    /// # Errors:
+    /// - If a token of the template has no value, an `std::io::Error` with
+    ///   `InvalidInput` kind is returned.
+    /// - If the root directory cannot be read.
+    pub fn discover(
+        &self,
+        root_dir: impl AsRef<Path>,
+        extension: &str,
+    ) -> Result<Vec<DiscoveredFile>, std::io::Error> {
+        self.validate()?;
+        let root_dir = root_dir.as_ref();
+        let parent = self.parent();
+        let suffix = format!(".{extension}");
+        let mut paths = Vec::new();
+        walk(root_dir, &mut paths)?;
+        let mut files = paths
+            .into_iter()
+            .filter_map(|path| {
+                let relative = path
+                    .strip_prefix(root_dir)
+                    .ok()?
+                    .components()
+                    .map(|c| c.as_os_str().to_str())
+                    .collect::<Option<Vec<_>>>()?
+                    .join("/");
+                if let Some(stem) = relative.strip_suffix(&suffix) {
+                    self.match_path(path.clone(), stem, false)
+                } else if let Some(stem) = relative.strip_suffix(ARCHIVE_EXTENSION) {
+                    parent.as_ref()?.match_path(path.clone(), stem, true)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        files.sort_by_key(|file| (file.start(), file.exec, file.seq, file.path.clone()));
+        Ok(files)
+    }
+
+    /// Match a relative path without extension against the template.
+    fn match_path(&self, path: PathBuf, stem: &str, archived: bool) -> Option<DiscoveredFile> {
+        let mut rest = stem;
+        let mut times = Vec::new();
+        let (mut exec, mut seq) = (None, None);
+        for field in &self.fields {
+            match field {
+                Field::Literal(text) => rest = rest.strip_prefix(text.as_str())?,
+                Field::Token(name) => rest = rest.strip_prefix(self.tokens.get(name)?.as_str())?,
+                Field::Time(spec) => {
+                    let text = rest.get(..Field::time_width(*spec))?;
+                    let digits = match *spec {
+                        'z' => text.strip_prefix(['+', '-'])?,
+                        _ => text,
+                    };
+                    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                    }
+                    times.push((*spec, text));
+                    rest = &rest[text.len()..];
+                }
+                Field::Exec(width) | Field::Seq(width) => {
+                    let len = rest.bytes().take_while(u8::is_ascii_digit).count();
+                    if len == 0 || len < *width {
+                        return None;
+                    }
+                    let value = rest[..len].parse().ok()?;
+                    match field {
+                        Field::Exec(_) => exec = Some(value),
+                        _ => seq = Some(value),
+                    }
+                    rest = &rest[len..];
+                }
+            }
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+        let (local, offset) = match times.is_empty() {
+            true => (None, None),
+            false => parse_start(&times)?,
+        };
+        Some(DiscoveredFile {
+            path,
+            local,
+            offset,
+            exec,
+            seq,
+            archived,
+        })
+    }
+}
+
+impl std::fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Wall clock start and UTC offset of a time slot from the `strftime` fields of a path.
+///
+/// Missing fields default to the start of the period of the fields found, e.g. the
+/// first day of the month. Paths with inconsistent fields are not matched.
+fn parse_start(times: &[(char, &str)]) -> Option<(Option<NaiveDateTime>, Option<FixedOffset>)> {
+    let format = times
+        .iter()
+        .map(|(spec, _)| format!("%{spec}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = times
+        .iter()
+        .map(|(_, text)| *text)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut parsed = Parsed::new();
+    parse(&mut parsed, &text, StrftimeItems::new(&format)).ok()?;
+    let offset = parsed.offset().and_then(FixedOffset::east_opt);
+    let has_date = parsed.month().is_some() || parsed.ordinal().is_some();
+    if parsed.isoweek().is_some() && parsed.weekday().is_none() && !has_date {
+        parsed.set_weekday(Weekday::Mon).ok()?;
+    }
+    let has_year = parsed.year().is_some() || parsed.year_mod_100().is_some();
+    if has_year && !has_date && parsed.isoweek().is_none() {
+        parsed.set_month(1).ok()?;
+    }
+    if parsed.month().is_some() && parsed.day().is_none() {
+        parsed.set_day(1).ok()?;
+    }
+    if parsed.hour_div_12().is_none() {
+        parsed.set_hour(0).ok()?;
+    }
+    if parsed.minute().is_none() {
+        parsed.set_minute(0).ok()?;
+    }
+    let local = match parsed.to_naive_date() {
+        Ok(date) => Some(date.and_time(parsed.to_naive_time().ok()?)),
+        Err(_) => None,
+    };
+    Some((local, offset))
+}
+
+/// Collect the files below `dir`, recursively.
+fn walk(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Data file or tarball found using [PathTemplate::discover].
+pub struct DiscoveredFile {
+    /// Path to the file.
+    pub path: PathBuf,
+    /// Wall clock start of the time slot of the file, if the path contains a date.
+    pub local: Option<NaiveDateTime>,
+    /// UTC offset of the start of the time slot, if the path contains `%z`.
+    pub offset: Option<FixedOffset>,
+    /// Execution count, if the path contains `{exec}`.
+    pub exec: Option<u64>,
+    /// Sequence number, if the path contains `{seq}`.
+    pub seq: Option<u64>,
+    /// Whether the file is a tarball of a compressed directory.
+    pub archived: bool,
+}
+
+impl DiscoveredFile {
+    /// Start of the time slot of the file, assuming UTC if the path does not contain `%z`.
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        let offset = self.offset.map_or(0, |offset| offset.local_minus_utc());
+        self.local
+            .map(|local| (local - TimeDelta::seconds(offset as i64)).and_utc())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_template() {
+        let template = PathTemplate::new("%Y/%m/%d/{station}_%Y%m%dT%H")
+            .unwrap()
+            .token("station", "north");
+        let start = Utc
+            .with_ymd_and_hms(2025, 1, 2, 3, 0, 0)
+            .unwrap()
+            .fixed_offset();
+        assert_eq!(
+            template.render(&start, 0, 0),
+            PathBuf::from("2025/01/02/north_20250102T03")
+        );
+        let file = template
+            .match_path("x".into(), "2025/01/02/north_20250102T03", false)
+            .unwrap();
+        assert_eq!(file.start(), Some(start.to_utc()));
+        // inconsistent dates are not matched
+        assert!(template
+            .match_path("x".into(), "2025/01/03/north_20250102T03", false)
+            .is_none());
+        assert!(template
+            .match_path("x".into(), "2025/01/02/south_20250102T03", false)
+            .is_none());
+
+        let template = PathTemplate::new("{exec:4}/%GW%V/%Y%m%d%H%M%S%z_{seq}").unwrap();
+        let start = FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 1, 1, 1, 0, 0)
+            .unwrap();
+        let path = template.render(&start, 7, 12);
+        assert_eq!(path, PathBuf::from("0007/2025W01/20250101010000+0100_12"));
+        let file = template
+            .match_path("x".into(), path.to_str().unwrap(), false)
+            .unwrap();
+        assert_eq!((file.exec, file.seq), (Some(7), Some(12)));
+        assert_eq!(file.start(), Some(start.to_utc()));
+        let dir = template
+            .parent()
+            .unwrap()
+            .match_path("x".into(), "0007/2025W01", true)
+            .unwrap();
+        assert_eq!(
+            dir.start(),
+            Some(Utc.with_ymd_and_hms(2024, 12, 30, 0, 0, 0).unwrap())
+        );
+
+        assert!(PathTemplate::new("%Y/%q").is_err());
+        assert!(PathTemplate::new("/%Y").is_err());
+        assert!(PathTemplate::new("%Y/{station").is_err());
+        assert!(PathTemplate::new("%Y/{station:2}").is_err());
+        assert!(PathTemplate::new("{station}").unwrap().validate().is_err());
+    }
+}
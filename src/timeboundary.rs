@@ -1,40 +1,144 @@
+use chrono::Utc;
 use lazy_static::lazy_static;
-use serde::Serialize;
 use std::{
-    io::Write,
+    ffi::OsStr,
+    fs::File,
+    io::{IoSlice, Write},
     marker::PhantomData,
     path::PathBuf,
     sync::{mpsc, Arc, Mutex},
     thread::JoinHandle,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    formats::store_binary,
-    utils::{find_max_iter, get_compressor},
-    Binary, FmtInfo, Json,
+    formats::store_binary_vectored,
+    framewriter::Framing,
+    slot::{CustomSlots, DailySlots, HourlySlots, MinutelySlots, SlotLength, SlotPreset},
+    utils::{
+        check_schema, find_last, get_compressor, open_append, resumed_elapsed, run_dir,
+        write_all_vectored,
+    },
+    Anchor, Binary, Encoder, FmtInfo, FrameStream, Raw, RunManifest, StoreOptions, TimeSlot,
 };
 
-/// Data storage configuration for frames following a daily boundary.
+/// Seconds in a day of elapsed time.
+const DAY: u64 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// Time slot of a file of an ExecCount store, counted from the beginning of execution.
+///
+/// Slots restart at the beginning of every day of elapsed time, so the last
+/// slot of a day is shorter if the slot length does not divide a day.
+pub(crate) struct ElapsedSlot {
+    /// Number of whole days elapsed.
+    pub(crate) day: u32,
+    /// Number of the slot within the day.
+    pub(crate) slot: u32,
+}
+
+impl ElapsedSlot {
+    /// Slot of length `length` containing the elapsed time `tdelta`.
+    pub(crate) fn locate(tdelta: Duration, length: Duration) -> Self {
+        let day = tdelta.as_secs() / DAY;
+        let within = tdelta - Duration::from_secs(day * DAY);
+        Self {
+            day: day as u32,
+            slot: (within.as_nanos() / length.as_nanos()) as u32,
+        }
+    }
+
+    /// Elapsed time at the start and end of the slot.
+    pub(crate) fn span(&self, length: Duration) -> (Duration, Duration) {
+        let day = Duration::from_secs(self.day as u64 * DAY);
+        let start = day + length * self.slot;
+        (start, (start + length).min(day + Duration::from_secs(DAY)))
+    }
+}
+
+/// Data storage configuration for frames following time slots of a configurable
+/// [SlotLength], counted from the beginning of execution.
 /// This struct is used to store data frames in a directory
 /// structure relative to the root as follows:
-/// /path/to/root/<exec count [u32]>/<day count [u32]>/<day count [u32]>.{EXTENSION}
+/// /path/to/root/<exec count [u32]>/<day count [u32]>/<slot number [u32]>.{EXTENSION}
+///
+/// where the slot number counts the time slots within the day. Stores of daily
+/// files, such as [ExecCountDaily], name the file after the day count instead.
+/// Slot lengths up to a day are supported. If the slot length does not divide a
+/// day, the last slot of each day is shorter.
+///
+/// New files are created when [ExecCountSlotted::store] is called after the time slot
+/// of the current file has elapsed. Frames with an elapsed time earlier than the
+/// time slot of the current file are stored in the current file.
+///
+/// The time elapsed since the beginning of execution is passed to the store methods,
+/// e.g. to replay recorded data, or measured by the store using [ExecCountSlotted::store_now].
+///
+/// If compression is enabled, the directory of each day is compressed into a tarball
+/// once the store moves on to the next day.
+///
+/// Each store starts a new run by default. An existing run can be resumed using
+/// [StoreOptions::run], e.g. after the process restarts, in which case frames are
+/// appended to the latest file of the run. The run is described by the
+/// [RunManifest] in its directory.
+///
+/// [ExecCountMinutely], [ExecCountHourly] and [ExecCountDaily] are presets of this store.
 ///
-/// New files are created when [ExecCountDaily::store] is called after an integral day has elapsed
-/// since the creation of the store configuration.
-pub struct ExecCountDaily<Kind> {
+/// Usage:
+/// ```rust,no_run
+/// use datastor::{ExecCountSlotted, Binary, SlotLength};
+/// use chrono::Duration;
+/// // 10 minute files
+/// let mut store = ExecCountSlotted::<Binary>::new(
+///     "test",
+///     true,
+///     "testprogram",
+///     SlotLength::Duration(Duration::minutes(10)),
+/// )
+/// .unwrap();
+/// store.store_now(&[1, 2, 3]).unwrap();
+/// ```
+pub struct ExecCountSlotted<Kind, Preset = CustomSlots> {
     root_dir: PathBuf,
-    daycount: u32,
+    slot: Duration,
+    name: &'static str,
+    current: Option<ElapsedSlot>,
     last_dir: PathBuf,
     compress_tx: Option<mpsc::Sender<Option<PathBuf>>>,
     compress_hdl: Option<JoinHandle<()>>,
-    writer: Option<Box<dyn Write>>,
+    writer: Option<File>,
     progname: &'static str,
-    _marker: PhantomData<Kind>,
+    options: StoreOptions,
+    start: Instant,
+    offset: Duration,
+    manifest: RunManifest,
+    entry: usize,
+    _marker: PhantomData<(Kind, Preset)>,
 }
 
-impl<Kind> Drop for ExecCountDaily<Kind> {
+/// [ExecCountSlotted] store of minute files, grouped into daily directories.
+pub type ExecCountMinutely<Kind> = ExecCountSlotted<Kind, MinutelySlots>;
+
+/// [ExecCountSlotted] store of hourly files, grouped into daily directories:
+/// /path/to/root/<exec count [u32]>/<day count [u32]>/<hour count [u16]>.{EXTENSION}
+pub type ExecCountHourly<Kind> = ExecCountSlotted<Kind, HourlySlots>;
+
+/// [ExecCountSlotted] store of daily files:
+/// /path/to/root/<exec count [u32]>/<day count [u32]>/<day count [u32]>.{EXTENSION}
+pub type ExecCountDaily<Kind> = ExecCountSlotted<Kind, DailySlots>;
+
+impl<Kind, Preset> Drop for ExecCountSlotted<Kind, Preset> {
     fn drop(&mut self) {
+        let anchor = Anchor {
+            elapsed: self.offset + self.start.elapsed(),
+            utc: Utc::now(),
+        };
+        if let Err(e) = self.manifest.push_anchor(anchor) {
+            log::warn!("Error anchoring the run {:?}: {e:?}", self.root_dir);
+        }
+        if let Err(e) = self.manifest.close(&self.root_dir) {
+            log::warn!("Error closing the manifest of {:?}: {e:?}", self.root_dir);
+        }
         if let Some(tx) = &self.compress_tx {
             let _ = tx.send(None);
         }
@@ -44,95 +148,284 @@ impl<Kind> Drop for ExecCountDaily<Kind> {
     }
 }
 
-impl<Kind: FmtInfo> ExecCountDaily<Kind> {
+impl<Kind: FmtInfo> ExecCountSlotted<Kind> {
     #[must_use = "The errors must be handled."]
     /// Create a new storage configuration.
     ///
     /// # Arguments:
     /// - `root_dir`: Root directory where data files will be stored.
-    /// - `compress`: Whether individual, hourly files will be compressed into a tarball at the end of the day.
+    /// - `compress`: Whether the files of each day will be compressed into a tarball at the end of the day.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `slot`: Length of the time slot covered by each file.
+    ///
+    /// # Errors:
+    /// - If the slot length is not positive, is longer than a day, or is a calendar week
+    ///   or month, an `std::io::Error` with `InvalidInput` kind is returned.
+    /// - If the root directory cannot be created.
     pub fn new(
         root_dir: &str,
         compress: bool,
         progname: &'static str,
+        slot: SlotLength,
+    ) -> Result<Self, std::io::Error> {
+        Self::new_with_options(root_dir, compress, progname, slot, StoreOptions::default())
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the files of each day will be compressed into a tarball at the end of the day.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `slot`: Length of the time slot covered by each file.
+    /// - `options`: Options applied to the files created by this store.
+    ///
+    /// # Errors:
+    /// - If the slot length is not positive, is longer than a day, or is a calendar week
+    ///   or month, an `std::io::Error` with `InvalidInput` kind is returned.
+    /// - If the root directory cannot be created.
+    pub fn new_with_options(
+        root_dir: &str,
+        compress: bool,
+        progname: &'static str,
+        slot: SlotLength,
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
+        let slot = slot.validate()?.elapsed().ok_or(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Slot length must be at most a day, got {slot:?}"),
+        ))?;
+        Self::create(
+            root_dir,
+            compress,
+            progname,
+            (slot, "ExecCountSlotted"),
+            options,
+        )
+    }
+}
+
+impl<Kind: FmtInfo, Preset: SlotPreset> ExecCountSlotted<Kind, Preset> {
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the files of each day will be compressed into a tarball at the end of the day.
+    /// - `progname`: Name of the program creating this data file.
+    pub fn new(
+        root_dir: &str,
+        compress: bool,
+        progname: &'static str,
+    ) -> Result<Self, std::io::Error> {
+        Self::new_with_options(root_dir, compress, progname, StoreOptions::default())
+    }
+
+    #[must_use = "The errors must be handled."]
+    /// Create a new storage configuration with the given options.
+    ///
+    /// # Arguments:
+    /// - `root_dir`: Root directory where data files will be stored.
+    /// - `compress`: Whether the files of each day will be compressed into a tarball at the end of the day.
+    /// - `progname`: Name of the program creating this data file.
+    /// - `options`: Options applied to the files created by this store.
+    pub fn new_with_options(
+        root_dir: &str,
+        compress: bool,
+        progname: &'static str,
+        options: StoreOptions,
+    ) -> Result<Self, std::io::Error> {
+        let slot = Preset::SLOT
+            .elapsed()
+            .expect("Preset slot lengths are at most a day");
+        Self::create(
+            root_dir,
+            compress,
+            progname,
+            (slot, Preset::ELAPSED_NAME),
+            options,
+        )
+    }
+}
+
+impl<Kind: FmtInfo, Preset> ExecCountSlotted<Kind, Preset> {
+    fn create(
+        root_dir: &str,
+        compress: bool,
+        progname: &'static str,
+        (slot, name): (Duration, &'static str),
+        options: StoreOptions,
     ) -> Result<Self, std::io::Error> {
         std::fs::create_dir_all(root_dir)?;
         lazy_static! {
             static ref COMPRESSION_THREAD_TX: Arc<Mutex<Option<mpsc::Sender<Option<PathBuf>>>>> =
                 Arc::new(Mutex::new(None));
         }
-        let runcount = (find_max_iter(root_dir, None)? as u32)
-            .checked_add(1)
-            .ok_or({
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Failed to increment run count",
-                )
-            })?;
-        let root_dir = PathBuf::from(root_dir).join(format!("{runcount:0>10}"));
+        let (root_dir, resumed) = run_dir(root_dir, options.run)?; // root/runcount
         std::fs::create_dir_all(&root_dir)?;
-        let last_dir = root_dir.join(format!("{:0>10}", 0)); // root/runcount/daycount
-        std::fs::create_dir_all(&last_dir)?;
+        let manifest = RunManifest::start::<Kind>(&root_dir, Some(progname), name, &options)?;
         // handle compression
         let (compress_tx, compress_hdl) =
             get_compressor(compress, (*COMPRESSION_THREAD_TX).clone());
 
-        Ok(Self {
+        let mut store = Self {
+            last_dir: root_dir.join(format!("{:0>10}", 0)), // root/runcount/daycount
             root_dir,
-            daycount: 0,
-            last_dir,
+            slot,
+            name,
+            current: None,
             compress_tx,
             compress_hdl,
             writer: None,
             progname,
+            options,
+            start: Instant::now(),
+            offset: Duration::ZERO,
+            manifest,
+            entry: 0,
             _marker: PhantomData,
-        })
+        };
+        if resumed {
+            store.resume()?;
+        }
+        std::fs::create_dir_all(&store.last_dir)?;
+        store.anchor()?;
+        Ok(store)
     }
 
-    fn get_writer_checked(
-        &mut self,
-        tdelta: &Duration,
-    ) -> Result<&mut Box<dyn Write>, std::io::Error> {
-        let tdelta = tdelta.as_secs_f64();
-        let daycount = (tdelta / (24.0 * 3600.0)).floor() as u32;
-        if daycount > self.daycount {
-            // send for compression
+    /// Pick up the latest file of a resumed run, and the elapsed time of the run.
+    fn resume(&mut self) -> Result<(), std::io::Error> {
+        let Some((day, day_dir)) = find_last(&self.root_dir, None)? else {
+            return Ok(());
+        };
+        self.last_dir = day_dir;
+        let extension = OsStr::new(Kind::extension());
+        let Some((number, last)) = find_last(&self.last_dir, Some(extension))? else {
+            return Ok(());
+        };
+        let current = ElapsedSlot {
+            day: day as u32,
+            slot: match self.slot.as_secs() >= DAY {
+                true => 0,
+                false => number as u32,
+            },
+        };
+        log::debug!("Resuming {current:?} in {last:?}");
+        self.offset = resumed_elapsed(&self.manifest, current.span(self.slot).0, &last);
+        self.current = Some(current);
+        Ok(())
+    }
+
+    /// Length of the time slot covered by each file.
+    pub fn slot_length(&self) -> Duration {
+        self.slot
+    }
+
+    /// Manifest of the run, as written to the run directory when the store is dropped.
+    pub fn manifest(&self) -> &RunManifest {
+        &self.manifest
+    }
+
+    /// Record an anchor relating the elapsed time measured by the store, see
+    /// [ExecCountSlotted::elapsed], to the wall clock, and write it to the run manifest.
+    ///
+    /// Anchors are also recorded when the store is created and dropped, and
+    /// periodically when frames are stored, see [StoreOptions::anchor_interval].
+    ///
+    /// # Errors:
+    /// - If the run manifest cannot be written.
+    pub fn anchor(&mut self) -> Result<Anchor, std::io::Error> {
+        let anchor = Anchor {
+            elapsed: self.elapsed(),
+            utc: Utc::now(),
+        };
+        self.record_anchor(anchor)?;
+        Ok(anchor)
+    }
+
+    /// Record an anchor measured by the caller, e.g. for the elapsed times passed to
+    /// the store methods when they are not measured by the store, and write it to the
+    /// run manifest.
+    ///
+    /// # Errors:
+    /// - If the elapsed time of the anchor is earlier than the latest anchor, an
+    ///   `std::io::Error` with `InvalidInput` kind is returned.
+    /// - If the run manifest cannot be written.
+    pub fn record_anchor(&mut self, anchor: Anchor) -> Result<(), std::io::Error> {
+        self.manifest.anchor(&self.root_dir, anchor)
+    }
+
+    /// Time elapsed since the store was created, measured using a monotonic clock.
+    /// If the run was resumed, the time elapsed is counted from the start of the run.
+    ///
+    /// The elapsed time can be passed to the methods taking the time delta
+    /// since the beginning of execution, e.g. `store.store_vectored(&store.elapsed(), data)`.
+    pub fn elapsed(&self) -> Duration {
+        self.offset + self.start.elapsed()
+    }
+
+    /// Name of the file of a time slot within its day directory.
+    fn file_name(&self, slot: ElapsedSlot) -> String {
+        let number = match self.slot.as_secs() >= DAY {
+            true => slot.day,
+            false => slot.slot,
+        };
+        format!("{number:0>10}.{}", Kind::extension())
+    }
+
+    fn get_writer_checked(&mut self, tdelta: &Duration) -> Result<&mut File, std::io::Error> {
+        if self.manifest.anchor_due(self.elapsed(), &self.options) {
+            self.anchor()?;
+        }
+        let located = ElapsedSlot::locate(*tdelta, self.slot);
+        // never move back to an earlier time slot
+        let target = self.current.map_or(located, |current| current.max(located));
+        if self.current != Some(target) {
+            if self
+                .current
+                .is_some_and(|current| current.day != target.day)
             {
+                // send for compression
                 if let Some(tx) = &self.compress_tx {
                     let _ = tx.send(Some(self.last_dir.clone())); // if None is sent, the thread will exit
                 }
-                self.daycount = daycount;
-                self.last_dir =
-                    PathBuf::from(&self.root_dir).join(format!("{:0>10}", self.daycount));
-                std::fs::create_dir_all(&self.last_dir)?;
-                self.writer = None;
             }
+            self.last_dir = self.root_dir.join(format!("{:0>10}", target.day));
+            std::fs::create_dir_all(&self.last_dir)?;
+            self.current = Some(target);
+            self.writer = None;
         }
-        let filename = self
-            .last_dir
-            .join(format!("{:0>10}.{}", self.daycount, Kind::extension()));
-        if filename.exists() {
-            if self.writer.is_none() {
+        let filename = self.last_dir.join(self.file_name(target));
+        if self.writer.is_none() || !filename.exists() {
+            let (start, end) = target.span(self.slot);
+            let slot = TimeSlot::Elapsed { start, end };
+            let header = self
+                .options
+                .file_header::<Kind>(self.progname, self.name, slot);
+            if filename.exists() {
+                // reopen the file, e.g. the latest file of a resumed run
+                check_schema(&filename, &header)?;
+                let writer = open_append(&filename)?;
+                self.writer = Some(writer);
+                self.entry = self.manifest.file(&self.root_dir, &filename);
+            } else {
                 // create a new writer
-                let writer = std::fs::OpenOptions::new().append(true).open(&filename)?;
-                self.writer = Some(Box::new(writer));
+                let mut writer = File::create(&filename)?;
+                Kind::initialize_with(&mut writer, &header)?;
+                writer.flush()?;
+                self.writer = Some(writer);
+                self.entry = self.manifest.file(&self.root_dir, &filename);
             }
-        } else {
-            // create a new writer
-            let mut writer = std::fs::File::create(&filename)?;
-            Kind::initialize(&mut writer, self.progname)?;
-            writer.flush()?;
-            self.writer = Some(Box::new(writer));
         }
         Ok(self.writer.as_mut().unwrap())
     }
 }
 
-impl<T: Serialize> ExecCountDaily<Json<T>> {
-    /// Store a JSON-serialized data frame.
+impl<Kind: Encoder, Preset> ExecCountSlotted<Kind, Preset> {
+    /// Store a data frame stamped with the time elapsed since the store was created.
     ///
     /// # Arguments:
-    /// - `tdelta`: Time delta since the beginning of execution.
     /// - `data`: Data to be stored.
     ///
     /// # Returns:
@@ -140,28 +433,16 @@ impl<T: Serialize> ExecCountDaily<Json<T>> {
     /// - `Err(std::io::Error)` if there was an error during the process.
     ///
     /// # Errors:
-    /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
+    /// - If the data cannot be encoded, an `std::io::Error` is returned.
     /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
-    ///
-    pub fn store(
-        &mut self,
-        tdelta: &Duration,
-        data: &T,
-    ) -> core::result::Result<(), std::io::Error> {
-        let writer = self.get_writer_checked(tdelta)?;
-        serde_json::to_writer(writer.by_ref(), data)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
-        writer.write_all(Json::<T>::delimiter())?;
-        writer.flush()?;
-        Ok(())
+    pub fn store_now(&mut self, data: &Kind::Frame) -> Result<(), std::io::Error> {
+        self.store(&self.elapsed(), data)
     }
-}
 
-impl ExecCountDaily<Binary> {
-    /// Store a binary data frame.
+    /// Store a data frame.
     ///
     /// # Arguments:
-    /// - `tdelta`: Time delta since beginning of execution.
+    /// - `tdelta`: Time delta since the beginning of execution.
     /// - `data`: Data to be stored.
     ///
     /// # Returns:
@@ -169,185 +450,110 @@ impl ExecCountDaily<Binary> {
     /// - `Err(std::io::Error)` if there was an error during the process.
     ///
     /// # Errors:
-    /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
+    /// - If the data cannot be encoded, an `std::io::Error` is returned.
     /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
+    /// - If an existing file, e.g. the latest file of a resumed run, has a different schema
+    ///   or codec, an `std::io::Error` with `InvalidData` kind is returned.
     ///
-    pub fn store(&mut self, tdelta: &Duration, data: &[u8]) -> Result<(), std::io::Error> {
-        let writer = self.get_writer_checked(tdelta)?;
-        store_binary(writer, data)?;
-        Ok(())
-    }
-}
-
-/// Data storage configuration for frames following a daily boundary.
-/// This struct is used to store data frames in a directory
-/// structure relative to the root as follows:
-/// /path/to/root/<exec count [u32]>/<day count [u32]>/<hour count [u16]>.{EXTENSION}
-///
-/// New files are created when [ExecCountHourly::store] is called after an integral day has elapsed
-/// since the creation of the store configuration.
-pub struct ExecCountHourly<Kind> {
-    root_dir: PathBuf,
-    daycount: u32,
-    hourcount: u16,
-    last_dir: PathBuf,
-    compress_tx: Option<mpsc::Sender<Option<PathBuf>>>,
-    compress_hdl: Option<JoinHandle<()>>,
-    writer: Option<Box<dyn Write>>,
-    progname: &'static str,
-    _marker: PhantomData<Kind>,
-}
-
-impl<Kind> Drop for ExecCountHourly<Kind> {
-    fn drop(&mut self) {
-        if let Some(tx) = &self.compress_tx {
-            let _ = tx.send(None);
-        }
-        if let Some(handle) = self.compress_hdl.take() {
-            let _ = handle.join();
+    pub fn store(&mut self, tdelta: &Duration, data: &Kind::Frame) -> Result<(), std::io::Error> {
+        let first = self.get_writer_checked(tdelta)?.metadata()?.len() == 0;
+        let mut repr = Vec::new();
+        if first {
+            Kind::encode_first(&mut repr, data, &self.options)?;
+        } else {
+            Kind::encode(&mut repr, data, &self.options)?;
         }
+        let writer = self.writer.as_mut().unwrap(); // opened above
+        writer.write_all(&repr)?;
+        writer.flush()?;
+        self.manifest.files[self.entry].record(*tdelta);
+        Ok(())
     }
 }
 
-impl<Kind: FmtInfo> ExecCountHourly<Kind> {
-    #[must_use = "The errors must be handled."]
-    /// Create a new storage configuration.
+impl<Preset> ExecCountSlotted<Binary, Preset> {
+    /// Store a binary data frame gathered from multiple buffers.
+    ///
+    /// The frame size is computed across all buffers, and the buffers are
+    /// written using vectored writes without being concatenated first.
     ///
     /// # Arguments:
-    /// - `root_dir`: Root directory where data files will be stored.
-    /// - `compress`: Whether individual, hourly files will be compressed into a tarball at the end of the day.
-    pub fn new(
-        root_dir: &str,
-        compress: bool,
-        progname: &'static str,
-    ) -> Result<Self, std::io::Error> {
-        std::fs::create_dir_all(root_dir)?;
-        lazy_static! {
-            static ref COMPRESSION_THREAD_TX: Arc<Mutex<Option<mpsc::Sender<Option<PathBuf>>>>> =
-                Arc::new(Mutex::new(None));
-        }
-        let runcount = (find_max_iter(root_dir, None)? as u32)
-            .checked_add(1)
-            .ok_or({
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Failed to increment run count",
-                )
-            })?;
-        let root_dir = PathBuf::from(root_dir).join(format!("{runcount:0>10}"));
-        std::fs::create_dir_all(&root_dir)?;
-        let last_dir = root_dir.join(format!("{:0>10}", 0)); // root/runcount/daycount
-        std::fs::create_dir_all(&last_dir)?;
-        // handle compression
-        let (compress_tx, compress_hdl) =
-            get_compressor(compress, (*COMPRESSION_THREAD_TX).clone());
-
-        Ok(Self {
-            root_dir, // root/runcount
-            daycount: 0,
-            hourcount: 0,
-            last_dir, // root/runcount/daycount
-            compress_tx,
-            compress_hdl,
-            writer: None,
-            progname,
-            _marker: PhantomData,
-        })
-    }
-
-    fn get_writer_checked(
+    /// - `tdelta`: Time delta since beginning of execution.
+    /// - `data`: Buffers that make up the data to be stored.
+    ///
+    /// # Returns:
+    /// - `Ok(())` if the data was stored successfully.
+    /// - `Err(std::io::Error)` if there was an error during the process.
+    pub fn store_vectored(
         &mut self,
         tdelta: &Duration,
-    ) -> Result<&mut Box<dyn Write>, std::io::Error> {
-        let tdelta = tdelta.as_secs_f64();
-        let daycount = (tdelta / (24.0 * 3600.0)).floor() as u32;
-        let tdelta = tdelta - (daycount as f64 * 24.0 * 3600.0);
-        let hourcount = (tdelta / 3600.0).floor() as u16;
-        if daycount > self.daycount {
-            // send for compression
-            {
-                if let Some(tx) = &self.compress_tx {
-                    let _ = tx.send(Some(self.last_dir.clone())); // if None is sent, the thread will exit
-                }
-                self.daycount = daycount;
-                self.last_dir =
-                    PathBuf::from(&self.root_dir).join(format!("{:0>10}", self.daycount));
-                std::fs::create_dir_all(&self.last_dir)?;
-                self.writer = None;
-            }
-        }
-        if hourcount > self.hourcount {
-            self.hourcount = hourcount;
-            self.writer = None;
-        }
-        let filename = self
-            .last_dir
-            .join(format!("{:0>10}.{}", self.hourcount, Kind::extension()));
-        if filename.exists() {
-            if self.writer.is_none() {
-                // create a new writer
-                let writer = std::fs::OpenOptions::new().append(true).open(&filename)?;
-                self.writer = Some(Box::new(writer));
-            }
-        } else {
-            // create a new writer
-            let mut writer = std::fs::File::create(&filename)?;
-            Kind::initialize(&mut writer, self.progname)?;
-            writer.flush()?;
-            self.writer = Some(Box::new(writer));
-        }
-        Ok(self.writer.as_mut().unwrap())
+        data: &[IoSlice<'_>],
+    ) -> Result<(), std::io::Error> {
+        let (version, codec) = (self.options.binary_version, self.options.codec);
+        let writer = self.get_writer_checked(tdelta)?;
+        store_binary_vectored(writer, data, version, codec)?;
+        self.manifest.files[self.entry].record(*tdelta);
+        Ok(())
     }
-}
 
-impl<T: Serialize> ExecCountHourly<Json<T>> {
-    /// Store a JSON-serialized data frame.
+    /// Stream a binary data frame of unknown size.
     ///
     /// # Arguments:
-    /// - `tdelta`: Time delta since the beginning of execution.
-    /// - `data`: Data to be stored.
+    /// - `tdelta`: Time delta since beginning of execution.
     ///
     /// # Returns:
-    /// - `Ok(())` if the data was stored successfully.
+    /// - `Ok(FrameStream)` to write the frame payload into. The frame is stored once
+    ///   [FrameStream::finish] is called, and discarded if the stream is dropped.
     /// - `Err(std::io::Error)` if there was an error during the process.
+    pub fn store_stream(&mut self, tdelta: &Duration) -> Result<FrameStream<'_>, std::io::Error> {
+        let (version, codec) = (self.options.binary_version, self.options.codec);
+        self.get_writer_checked(tdelta)?;
+        let writer = self.writer.as_mut().unwrap();
+        let (file, tdelta) = (&mut self.manifest.files[self.entry], *tdelta);
+        Ok(FrameStream::new(writer, Framing::Binary(version, codec))?
+            .on_finish(Box::new(move || file.record(tdelta))))
+    }
+}
+
+impl<Preset> ExecCountSlotted<Raw, Preset> {
+    /// Store data gathered from multiple buffers without any delimiters.
     ///
-    /// # Errors:
-    /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
-    /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
+    /// # Arguments:
+    /// - `tdelta`: Time delta since beginning of execution.
+    /// - `data`: Buffers that make up the data to be stored.
     ///
-    pub fn store(
+    /// # Returns:
+    /// - `Ok(())` if the data was stored successfully.
+    /// - `Err(std::io::Error)` if there was an error during the process.
+    pub fn store_vectored(
         &mut self,
         tdelta: &Duration,
-        data: &T,
-    ) -> core::result::Result<(), std::io::Error> {
+        data: &[IoSlice<'_>],
+    ) -> Result<(), std::io::Error> {
         let writer = self.get_writer_checked(tdelta)?;
-        serde_json::to_writer(writer.by_ref(), data)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
-        writer.write_all(Json::<T>::delimiter())?;
+        write_all_vectored(writer, &mut data.to_vec())?;
         writer.flush()?;
+        self.manifest.files[self.entry].record(*tdelta);
         Ok(())
     }
-}
 
-impl ExecCountHourly<Binary> {
-    /// Store a binary data frame.
+    /// Stream data of unknown size without any delimiters.
     ///
     /// # Arguments:
     /// - `tdelta`: Time delta since beginning of execution.
-    /// - `data`: Data to be stored.
     ///
     /// # Returns:
-    /// - `Ok(())` if the data was stored successfully.
+    /// - `Ok(FrameStream)` to write the data into. The data is kept once
+    ///   [FrameStream::finish] is called, and discarded if the stream is dropped.
     /// - `Err(std::io::Error)` if there was an error during the process.
-    ///
-    /// # Errors:
-    /// - If the file already exists, an `std::io::Error` with `AlreadyExists` kind is returned.
-    /// - If there was an error during file creation or writing, an `std::io::Error` is returned.
-    ///
-    pub fn store(&mut self, tdelta: &Duration, data: &[u8]) -> Result<(), std::io::Error> {
-        let writer = self.get_writer_checked(tdelta)?;
-        store_binary(writer, data)?;
-        Ok(())
+    pub fn store_stream(&mut self, tdelta: &Duration) -> Result<FrameStream<'_>, std::io::Error> {
+        self.get_writer_checked(tdelta)?;
+        let writer = self.writer.as_mut().unwrap();
+        let (file, tdelta) = (&mut self.manifest.files[self.entry], *tdelta);
+        Ok(
+            FrameStream::new(writer, Framing::Raw)?
+                .on_finish(Box::new(move || file.record(tdelta))),
+        )
     }
 }
 
@@ -356,6 +562,7 @@ mod tests {
     use std::time::Duration;
 
     use super::*;
+    use crate::{Json, Run};
 
     #[test]
     fn test_store_hourly() {
@@ -371,6 +578,107 @@ mod tests {
         std::fs::remove_dir_all(dir).unwrap_or_default();
     }
 
+    #[test]
+    fn test_store_stream() {
+        let dir = "test_store_stream_exechourly";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = ExecCountHourly::<Binary>::new(dir, false, "test").unwrap();
+        store.store(&Duration::from_secs(1), &[1, 2, 3]).unwrap();
+        let len = store.writer.as_ref().unwrap().metadata().unwrap().len();
+        let mut stream = store.store_stream(&Duration::from_secs(2)).unwrap();
+        stream.write_all(&[4, 5]).unwrap();
+        drop(stream); // discarded
+        let mut stream = store.store_stream(&Duration::from_secs(3)).unwrap();
+        stream.write_all(&[4, 5]).unwrap();
+        stream.write_all(&[6, 7, 8]).unwrap();
+        stream.finish().unwrap();
+        store.store(&Duration::from_secs(4), &[9]).unwrap();
+        let filename = PathBuf::from(dir)
+            .join(format!("{:0>10}", 1))
+            .join(format!("{:0>10}", 0))
+            .join(format!("{:0>10}.bin", 0));
+        let data = std::fs::read(filename).unwrap();
+        let frame = &data[len as usize..];
+        assert_eq!(&frame[..4], b"FRME");
+        assert_eq!(u32::from_le_bytes(frame[4..8].try_into().unwrap()), 12);
+        assert_eq!(u32::from_le_bytes(frame[8..12].try_into().unwrap()), 5);
+        assert_eq!(&frame[12..17], &[4, 5, 6, 7, 8]);
+        assert_eq!(&frame[17..20], &[0xff; 3]);
+        assert_eq!(&frame[20..24], b"FRME");
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_store_raw() {
+        let dir = "test_store_raw_exechourly";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = ExecCountHourly::<Raw>::new(dir, false, "test").unwrap();
+        store.store(&Duration::from_secs(1), b"abc").unwrap();
+        store
+            .store_vectored(
+                &Duration::from_secs(2),
+                &[IoSlice::new(b"de"), IoSlice::new(b"f")],
+            )
+            .unwrap();
+        let mut stream = store.store_stream(&Duration::from_secs(3)).unwrap();
+        stream.write_all(b"gh").unwrap();
+        stream.finish().unwrap();
+        let filename = PathBuf::from(dir)
+            .join(format!("{:0>10}", 1))
+            .join(format!("{:0>10}", 0))
+            .join(format!("{:0>10}.raw", 0));
+        assert_eq!(std::fs::read(filename).unwrap(), b"abcdefgh");
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_store_encoder() {
+        struct Line {}
+
+        impl FmtInfo for Line {
+            fn delimiter() -> &'static [u8] {
+                b"\n"
+            }
+
+            fn extension() -> &'static str {
+                "txt"
+            }
+
+            fn initialize<W: Write>(mut writer: W, progname: &str) -> std::io::Result<W> {
+                writeln!(writer, "# {progname}")?;
+                Ok(writer)
+            }
+        }
+
+        impl Encoder for Line {
+            type Frame = str;
+
+            fn encode<W: Write>(
+                writer: &mut W,
+                frame: &str,
+                _options: &StoreOptions,
+            ) -> std::io::Result<()> {
+                writer.write_all(frame.as_bytes())?;
+                writer.write_all(Self::delimiter())
+            }
+        }
+
+        let dir = "test_store_encoder_execdaily";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = ExecCountDaily::<Line>::new(dir, false, "test").unwrap();
+        store.store(&Duration::from_secs(1), "first").unwrap();
+        store.store(&Duration::from_secs(2), "second").unwrap();
+        let filename = PathBuf::from(dir)
+            .join(format!("{:0>10}", 1))
+            .join(format!("{:0>10}", 0))
+            .join(format!("{:0>10}.txt", 0));
+        assert_eq!(
+            std::fs::read_to_string(filename).unwrap(),
+            "# test\nfirst\nsecond\n"
+        );
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
     #[test]
     fn test_store_daily() {
         let dir = "test_store_execdaily";
@@ -384,4 +692,163 @@ mod tests {
             .unwrap(); // after a day and an hour
         std::fs::remove_dir_all(dir).unwrap_or_default();
     }
+
+    #[test]
+    fn test_store_now() {
+        let dir = "test_store_now_execdaily";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = ExecCountDaily::<Binary>::new(dir, false, "test").unwrap();
+        store.store_now(&[1, 2, 3]).unwrap();
+        store
+            .store_vectored(&store.elapsed(), &[IoSlice::new(&[4, 5])])
+            .unwrap();
+        assert!(store.elapsed() < Duration::from_secs(60));
+        let filename = PathBuf::from(dir)
+            .join(format!("{:0>10}", 1))
+            .join(format!("{:0>10}", 0))
+            .join(format!("{:0>10}.bin", 0));
+        let (_, frames) = crate::read_binary(filename).unwrap();
+        assert_eq!(frames, vec![vec![1, 2, 3], vec![4, 5]]);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_elapsed_slot() {
+        let hour = Duration::from_secs(3600);
+        let locate = |secs| {
+            let slot = ElapsedSlot::locate(Duration::from_secs(secs), hour);
+            (slot.day, slot.slot)
+        };
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(23 * 3600 + 1800), (0, 23));
+        assert_eq!(locate(24 * 3600), (1, 0));
+        assert_eq!(locate(25 * 3600 + 1), (1, 1));
+        assert_eq!(locate(49 * 3600), (2, 1));
+        // the last slot of a day is shorter
+        let seven = Duration::from_secs(7 * 3600);
+        let last = ElapsedSlot::locate(Duration::from_secs(23 * 3600), seven);
+        assert_eq!(last.slot, 3);
+        assert_eq!(
+            last.span(seven),
+            (
+                Duration::from_secs(21 * 3600),
+                Duration::from_secs(24 * 3600)
+            )
+        );
+        assert!(last < ElapsedSlot::locate(Duration::from_secs(24 * 3600), seven));
+    }
+
+    #[test]
+    fn test_store_hourly_rollover() {
+        let dir = "test_store_hourly_rollover";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = ExecCountHourly::<Json<u32>>::new(dir, false, "test").unwrap();
+        let hours = [0.5, 23.5, 24.5, 25.5, 49.5];
+        for (i, hours) in hours.iter().enumerate() {
+            store
+                .store(&Duration::from_secs_f64(hours * 3600.0), &(i as u32))
+                .unwrap();
+        }
+        // earlier frames are stored in the current file
+        store.store(&Duration::from_secs(3600), &5).unwrap();
+        let run = PathBuf::from(dir).join(format!("{:0>10}", 1));
+        let file = |day: u32, hour: u32| run.join(format!("{day:0>10}/{hour:0>10}.json"));
+        for (day, hour, records) in [(0, 0, 1), (0, 23, 1), (1, 0, 1), (1, 1, 1), (2, 1, 2)] {
+            let (header, frames) = crate::JsonReader::<u32>::new(0)
+                .read(file(day, hour))
+                .unwrap();
+            assert_eq!(frames.len(), records);
+            let start = Duration::from_secs((day * 24 + hour) as u64 * 3600);
+            assert_eq!(
+                header.slot,
+                Some(TimeSlot::Elapsed {
+                    start,
+                    end: start + Duration::from_secs(3600)
+                })
+            );
+        }
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_resume() {
+        let dir = "test_resume";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let hours = |hours: u64| Duration::from_secs(hours * 3600);
+        let mut store = ExecCountHourly::<Json<u32>>::new(dir, false, "test").unwrap();
+        store.store(&hours(1), &0).unwrap();
+        let latest = hours(26) + Duration::from_secs(1800);
+        store.store(&latest, &1).unwrap();
+        drop(store);
+        let options = StoreOptions::new().run(Run::Latest);
+        let mut store =
+            ExecCountHourly::<Json<u32>>::new_with_options(dir, false, "test", options).unwrap();
+        // continues from the latest frame stored, past the start of its time slot
+        assert!(store.elapsed() >= latest);
+        assert!(store.elapsed() < hours(27));
+        // earlier frames are appended to the latest file of the run
+        store.store(&hours(0), &2).unwrap();
+        store.store(&store.elapsed(), &3).unwrap();
+        let run = PathBuf::from(dir).join(format!("{:0>10}", 1));
+        let (_, frames) = crate::JsonReader::<u32>::new(0)
+            .read(run.join(format!("{:0>10}/{:0>10}.json", 1, 2)))
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+
+        // caller provided run
+        let options = StoreOptions::new().run(Run::Id(7));
+        let mut store =
+            ExecCountHourly::<Json<u32>>::new_with_options(dir, false, "test", options).unwrap();
+        store.store(&hours(0), &4).unwrap();
+        let run = PathBuf::from(dir).join(format!("{:0>10}", 7));
+        assert!(run.join(format!("{:0>10}/{:0>10}.json", 0, 0)).exists());
+        drop(store);
+        let mut store = ExecCountHourly::<Json<u32>>::new(dir, false, "test").unwrap();
+        store.store(&hours(0), &5).unwrap();
+        let run = PathBuf::from(dir).join(format!("{:0>10}", 8));
+        assert!(run.join(format!("{:0>10}/{:0>10}.json", 0, 0)).exists());
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_resume_schema() {
+        let dir = "test_resume_schema";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let store = |version| {
+            let options = StoreOptions::new()
+                .schema("resume")
+                .schema_version(version)
+                .run(Run::Latest);
+            ExecCountHourly::<Json<u32>>::new_with_options(dir, false, "test", options).unwrap()
+        };
+        let mut first = store(1);
+        first.store(&Duration::from_secs(1), &1).unwrap();
+        drop(first);
+        // the latest file of the run has a different schema version
+        let mut second = store(2);
+        let err = second.store(&Duration::from_secs(2), &2).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        drop(second);
+        let mut third = store(1);
+        third.store(&Duration::from_secs(3), &3).unwrap();
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_store_minutely() {
+        let dir = "test_store_minutely";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = ExecCountMinutely::<Json<u32>>::new(dir, false, "test").unwrap();
+        store.store(&Duration::from_secs(90), &1).unwrap();
+        store.store(&Duration::from_secs(86400 + 61), &2).unwrap();
+        let run = PathBuf::from(dir).join(format!("{:0>10}", 1));
+        assert!(run.join(format!("{:0>10}/{:0>10}.json", 0, 1)).exists());
+        assert!(run.join(format!("{:0>10}/{:0>10}.json", 1, 1)).exists());
+        drop(store);
+
+        assert!(ExecCountSlotted::<Json<u32>>::new(dir, false, "test", SlotLength::Month).is_err());
+        let two_days = SlotLength::Duration(chrono::TimeDelta::days(2));
+        assert!(ExecCountSlotted::<Json<u32>>::new(dir, false, "test", two_days).is_err());
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
 }
@@ -1,40 +1,12 @@
-use lazy_static::lazy_static;
-use serde::Serialize;
-use std::{
-    fs::File,
-    io::Write,
-    marker::PhantomData,
-    path::PathBuf,
-    sync::{mpsc, Arc, Mutex},
-    thread,
-};
+use crate::{slot::HourlySlots, Slotted};
 
-use chrono::{DateTime, Utc};
-
-use crate::{
-    formats::{store_binary, FmtInfo},
-    lock::LockFile,
-    utils::{get_compressor, get_lock, CheckedFileName, UtcDailyBoundary, UtcHourlyBoundary},
-    Binary, Json, Raw,
-};
-
-impl<T> Drop for UtcHourly<T> {
-    fn drop(&mut self) {
-        if let Some(tx) = &self.compress_tx {
-            if let Some(hdl) = self.compress_hdl.take() {
-                let _ = tx.send(None);
-                let _ = hdl.join();
-            }
-        }
-    }
-}
-
-/// Data storage configuration of some type. Currently, the type
-/// is either [Binary] or [Json].
+/// Data storage configuration of some type, such as [Binary](crate::Binary),
+/// [Json](crate::Json), [Raw](crate::Raw), or any user-defined type implementing
+/// [Encoder](crate::Encoder).
 ///
-/// This struct is used to store data frames in a directory
+/// This store is a [Slotted] preset, storing data frames in a directory
 /// structure relative to the root as follows:
-/// /path/to/root/YYYYMMDD/YYYYMMDDHHMM.{EXTENSION}
+/// /path/to/root/YYYYMMDD/YYYYMMDDHH0000.{EXTENSION}
 ///
 /// New files are created every UTC hour, and over the hour
 /// appends the data packets into the file.
@@ -54,202 +26,20 @@ impl<T> Drop for UtcHourly<T> {
 /// let _ = store.store(now, data.as_ref()).unwrap(); // first frame
 /// let _ = store.store(now + Duration::hours(2), data.as_ref()).unwrap(); // second frame
 /// let _ = store.store(now + Duration::hours(25), data.as_ref()).unwrap(); // third frame, this will trigger a compression event
-///
-pub struct UtcHourly<Kind> {
-    root_dir: PathBuf,
-    current_dir: PathBuf,
-    last_date: Option<String>,
-    last_hour: Option<String>,
-    compress_tx: Option<mpsc::Sender<Option<PathBuf>>>,
-    compress_hdl: Option<thread::JoinHandle<()>>,
-    writer: Option<File>,
-    progname: &'static str,
-    _lock: LockFile,
-    _marker: PhantomData<Kind>,
-}
-
-impl<Kind: FmtInfo> UtcHourly<Kind> {
-    #[must_use = "The errors must be handled."]
-    /// Create a new storage configuration.
-    ///
-    /// # Arguments:
-    /// - `root_dir`: Root directory where data files will be stored.
-    /// - `compress`: Whether individual, hourly files will be compressed into a tarball at the end of the day.
-    /// - `progname`: Name of the program creating this data file.
-    pub fn new(
-        root_dir: PathBuf,
-        compress: bool,
-        progname: &'static str,
-    ) -> Result<Self, std::io::Error> {
-        std::fs::create_dir_all(&root_dir)?;
-        let lock = get_lock(&root_dir, Kind::type_hash())?;
-        lazy_static! {
-            static ref COMPRESSION_THREAD_TX: Arc<Mutex<Option<mpsc::Sender<Option<PathBuf>>>>> =
-                Arc::new(Mutex::new(None));
-        }
-        // handle compression
-        let (compress_tx, compress_hdl) =
-            get_compressor(compress, (*COMPRESSION_THREAD_TX).clone());
-        Ok(Self {
-            root_dir,
-            current_dir: PathBuf::new(),
-            last_date: None,
-            last_hour: None,
-            writer: None,
-            compress_tx,
-            compress_hdl,
-            progname,
-            _lock: lock,
-            _marker: PhantomData,
-        })
-    }
-
-    fn get_writer_checked(
-        &mut self,
-        filename: &CheckedFileName,
-    ) -> Result<&mut File, std::io::Error> {
-        if !filename.exists() {
-            let writer = filename
-                .clone()
-                .get_writer_with_init(Kind::initialize, self.progname)?;
-            self.set_writer(Some(writer));
-        }
-        let writer = match self.writer.take() {
-            Some(writer) => writer,
-            None => filename
-                .clone()
-                .get_writer_with_init(Kind::initialize, self.progname)?,
-        };
-        self.set_writer(Some(writer));
-        Ok(self.get_writer().unwrap())
-    }
-}
-
-impl<Kind: FmtInfo> UtcDailyBoundary for UtcHourly<Kind> {
-    fn get_current_dir(&mut self) -> &PathBuf {
-        &self.current_dir
-    }
-
-    fn set_current_dir(&mut self, dir: PathBuf) {
-        self.current_dir = dir;
-    }
-
-    fn get_root_dir(&mut self) -> &PathBuf {
-        &self.root_dir
-    }
-
-    fn get_compressor(&mut self) -> Option<mpsc::Sender<Option<PathBuf>>> {
-        self.compress_tx.clone()
-    }
-
-    fn get_last_date(&mut self) -> Option<&str> {
-        self.last_date.as_deref()
-    }
-
-    fn set_last_date(&mut self, date: Option<String>) {
-        self.last_date = date;
-    }
-
-    fn get_writer(&mut self) -> Option<&mut File> {
-        self.writer.as_mut()
-    }
-
-    fn set_writer(&mut self, writer: Option<File>) {
-        self.writer = writer;
-    }
-}
-
-impl<Kind: FmtInfo> UtcHourlyBoundary for UtcHourly<Kind> {
-    fn get_last_hour(&mut self) -> Option<&str> {
-        self.last_hour.as_deref()
-    }
-
-    fn set_last_hour(&mut self, hour: Option<String>) {
-        self.last_hour = hour;
-    }
-}
-
-impl<T: Serialize> UtcHourly<Json<T>> {
-    #[must_use = "Errors must be handled."]
-    /// Store a JSON-serializable data frame.
-    ///
-    /// # Arguments:
-    /// - `tstamp`: Timestamp of the data frame, used to determine hourly and daily boundaries.
-    /// - `data`: Data to be stored, must implement [serde::Serialize].
-    ///
-    /// # Output:
-    /// - Returns the path to the file where the data was stored.
-    ///
-    /// # Errors:
-    /// - If the data cannot be serialized to JSON.
-    /// - If the file cannot be opened or written to.
-    /// - If the file cannot be flushed.
-    pub fn store(&mut self, tstamp: DateTime<Utc>, data: &T) -> Result<PathBuf, std::io::Error>
-    where
-        T: serde::Serialize,
-    {
-        let filename = self.check_time_utchourly::<Json<T>>(tstamp, false)?;
-        let writer = self.get_writer_checked(&filename)?;
-        serde_json::to_writer(writer.by_ref(), &data)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
-        writer.write_all(Json::<T>::delimiter())?;
-        writer.flush()?;
-        Ok(filename.into())
-    }
-}
-
-impl UtcHourly<Binary> {
-    #[must_use = "Errors must be handled."]
-    /// Store a binary data frame.
-    ///
-    /// # Arguments:
-    /// - `tstamp`: Timestamp of the data frame, used to determine hourly and daily boundaries.
-    /// - `data`: Data to be stored, must be a byte slice.
-    ///
-    /// # Output:
-    /// - Returns the path to the file where the data was stored.
-    ///
-    /// # Errors:
-    /// - If the data is too large (greater than 4 GiB).
-    /// - If the file cannot be opened or written to.
-    /// - If the file cannot be flushed.
-    pub fn store(&mut self, tstamp: DateTime<Utc>, data: &[u8]) -> Result<PathBuf, std::io::Error> {
-        let filename = self.check_time_utchourly::<Binary>(tstamp, false)?;
-        let writer = self.get_writer_checked(&filename)?;
-        store_binary(writer, data)?;
-        Ok(filename.into())
-    }
-}
-
-impl UtcHourly<Raw> {
-    #[must_use = "Errors must be handled"]
-    /// Store data without any delimiters.
-    ///
-    /// # Arguments:
-    /// - `tstamp`: Timestamp of the data frame, used to determine hourly and daily boundaries.
-    /// - `data`: Data to be stored.
-    ///
-    /// # Output:
-    /// - Returns the path to the file where the data was stored.
-    ///
-    /// # Errors:
-    /// - If the data cannot be serialized to JSON.
-    /// - If the file cannot be opened or written to.
-    /// - If the file cannot be flushed.
-    pub fn store(&mut self, tstamp: DateTime<Utc>, data: &[u8]) -> Result<PathBuf, std::io::Error> {
-        let filename = self.check_time_utcdaily::<Raw>(tstamp, false)?;
-        let writer = self.get_writer_checked(&filename)?;
-        writer.write_all(data)?;
-        writer.flush()?;
-        Ok(filename.into())
-    }
-}
+/// ```
+pub type UtcHourly<Kind> = Slotted<Kind, HourlySlots>;
 
 #[cfg(test)]
 mod tests {
-    use chrono::{Duration, Utc};
+    use chrono::{DateTime, Duration, Utc};
+    use std::io::IoSlice;
 
     use super::*;
+    #[cfg(feature = "csv")]
+    use crate::Csv;
+    #[cfg(feature = "parquet")]
+    use crate::Parquet;
+    use crate::{Binary, Json};
 
     #[test]
     fn test_store() {
@@ -276,6 +66,64 @@ mod tests {
         std::fs::remove_dir_all(dir).unwrap_or_default();
     }
 
+    #[test]
+    fn test_store_vectored() {
+        let dir = "test_store_vectored_utchourly";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = UtcHourly::<Binary>::new(dir.into(), false, "testprogram").unwrap();
+        let now = Utc::now();
+        let header = [1u8, 2, 3];
+        let samples = [4u8, 5, 6, 7, 8, 9];
+        let path = store.store(now, &[1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+        let len = std::fs::metadata(&path).unwrap().len() as usize;
+        let _ = store
+            .store_vectored(now, &[IoSlice::new(&header), IoSlice::new(&samples)])
+            .unwrap();
+        let data = std::fs::read(&path).unwrap();
+        let frame_len = len - data[..len].windows(4).rposition(|w| w == b"FRME").unwrap();
+        assert_eq!(data.len(), len + frame_len);
+        assert_eq!(data[len - frame_len..len], data[len..]);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_store_batch() {
+        let dir = "test_store_batch_utchourly";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = UtcHourly::<Json<u32>>::new(dir.into(), false, "testprogram").unwrap();
+        let start = DateTime::parse_from_rfc3339("2025-01-01T22:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let frames = (0..6).map(|i| (start + Duration::minutes(20 * i), i as u32));
+        let summary = store.store_batch(frames, true).unwrap();
+        assert_eq!(summary.frames(), 6);
+        let files = summary
+            .files
+            .iter()
+            .map(|(path, count)| (path.file_name().unwrap().to_str().unwrap(), *count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            vec![
+                ("20250101220000.json", 2),
+                ("20250101230000.json", 3),
+                ("20250102000000.json", 1)
+            ]
+        );
+        // going back to an existing file
+        let summary = store.store_batch([(start, &7)], false).unwrap();
+        assert_eq!(
+            summary.files[0].0,
+            std::path::Path::new(dir).join("20250101/20250101220000.json")
+        );
+        let data = std::fs::read_to_string(&summary.files[0].0).unwrap();
+        assert_eq!(
+            data.lines().skip(1).collect::<Vec<_>>(),
+            vec!["0", "1", "7"]
+        );
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
     #[test]
     fn test_string() {
         let dir = "test_string_utchourly";
@@ -292,4 +140,245 @@ mod tests {
             .unwrap();
         std::fs::remove_dir_all(dir).unwrap_or_default();
     }
+
+    #[cfg(any(feature = "zstd", feature = "lz4"))]
+    fn codec_roundtrip(dir: &str, codec: crate::Codec) {
+        use std::io::Write;
+
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let options = crate::StoreOptions::default().codec(codec);
+        let mut store =
+            UtcHourly::<Binary>::new_with_options(dir.into(), false, "testprogram", options)
+                .unwrap();
+        let now = Utc::now();
+        let data = vec![7u8; 4096];
+        let path = store.store(now, data.as_slice()).unwrap();
+        store.store_batch([(now, &data[..10])], false).unwrap();
+        let mut stream = store.store_stream(now).unwrap();
+        stream.write_all(&data[..100]).unwrap();
+        stream.finish().unwrap();
+        drop(store);
+        assert!(std::fs::metadata(&path).unwrap().len() < 4096);
+        let (header, frames) = crate::read_binary(&path).unwrap();
+        assert_eq!(header.codec, Some(codec));
+        assert_eq!(
+            frames,
+            vec![data.clone(), data[..10].to_vec(), data[..100].to_vec()]
+        );
+        // reopening the file with a different codec is refused
+        let mut store = UtcHourly::<Binary>::new(dir.into(), false, "testprogram").unwrap();
+        assert!(store.store(now, data.as_slice()).is_err());
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() {
+        codec_roundtrip("test_zstd_utchourly", crate::Codec::Zstd(3));
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_lz4() {
+        codec_roundtrip("test_lz4_utchourly", crate::Codec::Lz4);
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_csv() {
+        #[derive(serde::Serialize)]
+        struct Record {
+            id: u32,
+            #[serde(rename = "label")]
+            name: String,
+            value: f64,
+        }
+
+        #[derive(serde::Serialize)]
+        struct Nested {
+            id: u32,
+            #[serde(flatten)]
+            record: Record,
+        }
+
+        let dir = "test_csv_utchourly";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let mut store = UtcHourly::<Csv<Record>>::new(dir.into(), false, "testprogram").unwrap();
+        let now = Utc::now();
+        let first = Record {
+            id: 1,
+            name: "plain".into(),
+            value: 1.5,
+        };
+        let path = store.store(now, &first).unwrap();
+        let second = Record {
+            id: 2,
+            name: "comma, \"quote\"\nand newline".into(),
+            value: -2.0,
+        };
+        store.store_batch([(now, &second)], false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "id,label,value\n1,plain,1.5\n2,\"comma, \"\"quote\"\"\nand newline\",-2.0\n"
+        );
+        // reopening the file does not repeat the header
+        drop(store);
+        let mut store = UtcHourly::<Csv<Record>>::new(dir.into(), false, "testprogram").unwrap();
+        store.store(now, &second).unwrap();
+        let data = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(data.matches("id,label,value").count(), 1);
+        // records that are not flat are rejected
+        drop(store);
+        let mut store = UtcHourly::<Csv<Nested>>::new(dir.into(), false, "testprogram").unwrap();
+        let nested = Nested {
+            id: 3,
+            record: first,
+        };
+        let err = store.store(now + Duration::hours(1), &nested).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet() {
+        use parquet::{
+            arrow::arrow_reader::ParquetRecordBatchReaderBuilder, file::reader::FileReader,
+        };
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Record {
+            id: u32,
+            value: f64,
+        }
+
+        let rows = |path: &std::path::PathBuf| {
+            let builder =
+                ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path).unwrap())
+                    .unwrap();
+            let groups = builder.metadata().num_row_groups();
+            let rows = builder
+                .build()
+                .unwrap()
+                .map(|batch| batch.unwrap().num_rows())
+                .sum::<usize>();
+            (groups, rows)
+        };
+
+        let dir = "test_parquet_utchourly";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let tstamp = DateTime::parse_from_rfc3339("2025-01-01T22:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let options = crate::StoreOptions::new().row_group_size(2);
+        let mut store = UtcHourly::<Parquet<Record>>::new_with_options(
+            dir.into(),
+            false,
+            "testprogram",
+            options.clone(),
+        )
+        .unwrap();
+        let first = (0..3)
+            .map(|id| store.store(tstamp, &Record { id, value: 0.5 }).unwrap())
+            .last()
+            .unwrap();
+        assert!(!first.exists()); // still open
+        let second = store
+            .store(tstamp + Duration::hours(1), &Record { id: 3, value: 1.0 })
+            .unwrap();
+        assert_eq!(rows(&first), (2, 3));
+        let reader =
+            parquet::file::reader::SerializedFileReader::new(std::fs::File::open(&first).unwrap())
+                .unwrap();
+        let header = reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap()
+            .iter()
+            .find(|kv| kv.key == "datastor.header")
+            .and_then(|kv| kv.value.clone())
+            .unwrap();
+        let header: crate::FileHeader = serde_json::from_str(&header).unwrap();
+        assert_eq!(header.format, "parquet");
+        assert_eq!(header.store, "UtcHourly");
+        drop(store);
+        assert_eq!(rows(&second), (1, 1));
+        // reopening the time slot keeps the stored rows
+        let mut store = UtcHourly::<Parquet<Record>>::new_with_options(
+            dir.into(),
+            false,
+            "testprogram",
+            options,
+        )
+        .unwrap();
+        store
+            .store(tstamp + Duration::hours(1), &Record { id: 4, value: 2.0 })
+            .unwrap();
+        assert_eq!(store.close_file().unwrap(), Some(second.clone()));
+        assert_eq!(rows(&second).1, 2);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_recover() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Record {
+            id: u32,
+        }
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Other {
+            label: String,
+        }
+
+        let rows = |path: &std::path::PathBuf| {
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path).unwrap())
+                .unwrap()
+                .build()
+                .unwrap()
+                .map(|batch| batch.unwrap().num_rows())
+                .sum::<usize>()
+        };
+
+        let dir = "test_parquet_recover_utchourly";
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+        let tstamp = DateTime::parse_from_rfc3339("2025-01-01T22:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut store =
+            UtcHourly::<Parquet<Record>>::new(dir.into(), false, "testprogram").unwrap();
+        let path = (0..3)
+            .map(|id| store.store(tstamp, &Record { id }).unwrap())
+            .last()
+            .unwrap();
+        // the file cannot be moved into place
+        std::fs::create_dir(&path).unwrap();
+        assert!(store.close_file().is_err());
+        std::fs::remove_dir(&path).unwrap();
+        assert_eq!(store.close_file().unwrap(), Some(path.clone()));
+        assert_eq!(rows(&path), 3);
+        // a temporary file left behind by a failed close is carried over
+        store.store(tstamp, &Record { id: 3 }).unwrap();
+        std::fs::remove_file(&path).unwrap(); // its rows are in the temporary file
+        std::fs::create_dir(&path).unwrap();
+        drop(store);
+        std::fs::remove_dir(&path).unwrap();
+        let mut store =
+            UtcHourly::<Parquet<Record>>::new(dir.into(), false, "testprogram").unwrap();
+        store.store(tstamp, &Record { id: 4 }).unwrap();
+        assert_eq!(store.close_file().unwrap(), Some(path.clone()));
+        assert_eq!(rows(&path), 5);
+        drop(store);
+        // reopened files must have the same schema
+        let mut store = UtcHourly::<Parquet<Other>>::new(dir.into(), false, "testprogram").unwrap();
+        let other = Other {
+            label: "other".to_owned(),
+        };
+        let err = store.store(tstamp, &other).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_dir_all(dir).unwrap_or_default();
+    }
 }
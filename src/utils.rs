@@ -2,74 +2,22 @@
 use std::{
     ffi::OsStr,
     fs::{remove_dir_all, File, OpenOptions},
-    io::Write,
+    io::{IoSlice, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
     sync::{mpsc, Arc, Mutex},
     thread::{self, JoinHandle},
-    time::Instant,
+    time::{Duration, Instant, SystemTime},
 };
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, DurationRound, NaiveDate, NaiveTime, TimeDelta, Utc};
 use flate2::{write::GzEncoder, Compression};
 
 use cfg_if::cfg_if;
 
-use crate::{lock::LockFile, FmtInfo};
-
-pub(crate) trait UtcHourlyBoundary: UtcDailyBoundary {
-    fn get_last_hour(&mut self) -> Option<&str>;
-    fn set_last_hour(&mut self, hour: Option<String>);
-
-    fn check_time_utchourly<Kind: FmtInfo>(
-        &mut self,
-        tstamp: DateTime<Utc>,
-        single: bool,
-    ) -> Result<CheckedFileName, std::io::Error> {
-        let date = tstamp.format("%Y%m%d").to_string();
-        let hour = tstamp.format("%H").to_string();
-
-        if match self.get_last_date() {
-            Some(last_date) => {
-                if last_date != date {
-                    // Send the last directory to the compression thread
-                    if let Some(tx) = &self.get_compressor() {
-                        let _ = tx.send(Some(self.get_current_dir().clone()));
-                    }
-                    true
-                } else {
-                    false
-                }
-            }
-            None => true,
-        } {
-            // If the date has changed, create a new directory
-            let current_dir = self.get_root_dir().join(&date);
-            std::fs::create_dir_all(&current_dir)?;
-            self.set_current_dir(current_dir);
-            self.set_last_date(Some(date.clone()));
-            self.set_last_hour(None);
-        }
-
-        if self.get_last_hour() != Some(&hour) {
-            let current_dir = self.get_current_dir();
-            let filename = if single {
-                let hour = tstamp.format("%H%M%S.%f").to_string();
-                current_dir.join(format!("{}{}.{}", &date, &hour, Kind::extension()))
-            } else {
-                current_dir.join(format!("{}{}0000.{}", &date, &hour, Kind::extension()))
-            };
-            if filename.exists() {
-                return Ok(CheckedFileName::Old(filename));
-            } else {
-                return Ok(CheckedFileName::New(filename));
-            };
-        };
-        Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "Invalid time",
-        ))
-    }
-}
+use crate::{
+    archive::ArchiveQueue, lock::LockFile, read_header, FileHeader, FmtInfo, OutOfOrder, Run,
+    RunManifest,
+};
 
 pub(crate) trait UtcDailyBoundary {
     fn get_last_date(&mut self) -> Option<&str>;
@@ -77,29 +25,84 @@ pub(crate) trait UtcDailyBoundary {
     fn get_current_dir(&mut self) -> &PathBuf;
     fn set_current_dir(&mut self, dir: PathBuf);
     fn get_root_dir(&mut self) -> &PathBuf;
-    fn get_compressor(&mut self) -> Option<mpsc::Sender<Option<PathBuf>>>;
+    fn get_archive(&mut self) -> &mut ArchiveQueue;
+    fn get_latest(&mut self) -> Option<DateTime<Utc>>;
+    fn set_latest(&mut self, tstamp: Option<DateTime<Utc>>);
+    fn get_out_of_order(&mut self) -> OutOfOrder;
     fn get_writer(&mut self) -> Option<&mut File>;
     fn set_writer(&mut self, writer: Option<File>);
 
+    /// Register a frame with timestamp `tstamp`, stored in the current directory,
+    /// and release the directories whose grace period has passed.
+    fn register_utcdaily(&mut self, tstamp: DateTime<Utc>) {
+        let latest = self
+            .get_latest()
+            .map_or(tstamp, |latest| latest.max(tstamp));
+        self.set_latest(Some(latest));
+        let end = self
+            .get_last_date()
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+            .map_or(latest, |date| {
+                (date + TimeDelta::days(1))
+                    .and_time(NaiveTime::MIN)
+                    .and_utc()
+            });
+        self.get_archive().release(latest, end);
+    }
+
+    /// Route a frame with timestamp `tstamp` to its directory, and get its file name.
+    /// The frame must be registered using [UtcDailyBoundary::register_utcdaily] once stored.
     fn check_time_utcdaily<Kind: FmtInfo>(
         &mut self,
         tstamp: DateTime<Utc>,
         single: bool,
     ) -> Result<CheckedFileName, std::io::Error> {
-        let date = tstamp.format("%Y%m%d").to_string();
-        if let Some(last_date) = self.get_last_date() {
-            if last_date != date {
-                // Send the last directory to the compression thread
-                if let Some(tx) = &self.get_compressor() {
-                    let _ = tx.send(Some(self.get_current_dir().clone()));
-                }
+        let day =
+            |tstamp: DateTime<Utc>| tstamp.duration_trunc(TimeDelta::days(1)).unwrap_or(tstamp);
+        let latest = self
+            .get_latest()
+            .map_or(tstamp, |latest| latest.max(tstamp));
+        let current = day(self.get_latest().unwrap_or(tstamp));
+        // the day whose directory the frame is stored in
+        let dir_day = match self.get_out_of_order() {
+            _ if tstamp >= current => tstamp,
+            OutOfOrder::Backfill => tstamp,
+            OutOfOrder::Clamp => current,
+            OutOfOrder::Reject => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "Frame at {tstamp} is older than the current day, starting at {current}"
+                    ),
+                ))
             }
-        }
-        let hour = tstamp.format("%H%M%S.%f").to_string();
+        };
+        let date = dir_day.format("%Y%m%d").to_string();
         let current_dir = self.get_root_dir().join(&date);
+        if self.get_last_date() != Some(date.as_str()) {
+            let end = self.get_last_date().map(|last_date| {
+                NaiveDate::parse_from_str(last_date, "%Y%m%d")
+                    .map(|date| {
+                        (date + TimeDelta::days(1))
+                            .and_time(NaiveTime::MIN)
+                            .and_utc()
+                    })
+                    .unwrap_or(latest)
+            });
+            if let Some(end) = end {
+                // Queue the last directory for compression
+                let last_dir = self.get_current_dir().clone();
+                self.get_archive().push(last_dir, end);
+            }
+            self.get_archive().reopen(&current_dir)?;
+        }
         std::fs::create_dir_all(&current_dir)?;
         let filename = if single {
-            current_dir.join(format!("{}{}.{}", &date, hour, Kind::extension()))
+            current_dir.join(format!(
+                "{}.{}",
+                tstamp.format("%Y%m%d%H%M%S.%f"),
+                Kind::extension()
+            ))
         } else {
             current_dir.join(format!("{}000000.{}", &date, Kind::extension()))
         };
@@ -128,23 +131,23 @@ impl CheckedFileName {
     pub(crate) fn get_writer(&self) -> Result<File, std::io::Error> {
         match self {
             CheckedFileName::New(filename) => File::create(filename),
-            CheckedFileName::Old(filename) => OpenOptions::new().append(true).open(filename),
+            CheckedFileName::Old(filename) => open_append(filename),
         }
     }
 
-    pub(crate) fn get_writer_with_init<T: FnOnce(File, &'static str) -> std::io::Result<File>>(
+    pub(crate) fn get_writer_with_init<T: FnOnce(File, &FileHeader) -> std::io::Result<File>>(
         self,
         init: T,
-        progname: &'static str,
+        header: &FileHeader,
     ) -> Result<File, std::io::Error> {
         match self {
             CheckedFileName::New(filename) => {
                 let writer = File::create(filename)?;
-                let writer = init(writer, progname)?;
+                let writer = init(writer, header)?;
                 Ok(writer)
             }
             CheckedFileName::Old(filename) => {
-                let writer = OpenOptions::new().append(true).open(filename)?;
+                let writer = open_append(&filename)?;
                 Ok(writer)
             }
         }
@@ -174,6 +177,76 @@ impl From<CheckedFileName> for PathBuf {
     }
 }
 
+/// Make sure an existing data file was written using the schema and codec in `header`.
+///
+/// Files without a readable header are accepted.
+pub(crate) fn check_schema(filename: &Path, header: &FileHeader) -> Result<(), std::io::Error> {
+    match read_header(filename) {
+        Ok(existing) => check_header(filename, &existing, header),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Make sure the `existing` header of `filename` has the schema and codec in `header`.
+pub(crate) fn check_header(
+    filename: &Path,
+    existing: &FileHeader,
+    header: &FileHeader,
+) -> Result<(), std::io::Error> {
+    if existing.schema != header.schema || existing.schema_version != header.schema_version {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Schema mismatch in {filename:?}: found {:?} version {:?}, expected {:?} version {:?}",
+                existing.schema, existing.schema_version, header.schema, header.schema_version
+            ),
+        ));
+    }
+    if existing.codec != header.codec {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Codec mismatch in {filename:?}: found {:?}, expected {:?}",
+                existing.codec, header.codec
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Open an existing data file for appending.
+///
+/// The file is not opened in append mode, so that frame headers can be
+/// filled in after the payload of a [FrameStream](crate::FrameStream)
+/// has been written.
+pub(crate) fn open_append(filename: &Path) -> Result<File, std::io::Error> {
+    let mut writer = OpenOptions::new().write(true).open(filename)?;
+    writer.seek(SeekFrom::End(0))?;
+    Ok(writer)
+}
+
+/// Write all slices using vectored writes, retrying on partial writes.
+pub(crate) fn write_all_vectored<W: Write>(
+    writer: &mut W,
+    mut bufs: &mut [IoSlice<'_>],
+) -> Result<(), std::io::Error> {
+    IoSlice::advance_slices(&mut bufs, 0); // skip leading empty slices
+    while !bufs.is_empty() {
+        match writer.write_vectored(bufs) {
+            Ok(0) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::WriteZero,
+                    "Failed to write whole buffer",
+                ))
+            }
+            Ok(written) => IoSlice::advance_slices(&mut bufs, written),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn get_compressor(
     compress: bool,
     thread_tx: Arc<Mutex<Option<mpsc::Sender<Option<PathBuf>>>>>,
@@ -242,6 +315,7 @@ pub(crate) fn compressor(rx: mpsc::Receiver<Option<PathBuf>>) -> JoinHandle<()>
                         }
                     }
                 }
+                crate::archive::finished(&last_dir);
             } else {
                 break;
             }
@@ -256,35 +330,71 @@ pub(crate) fn get_lock(rootdir: &Path, hash: u64) -> Result<LockFile, std::io::E
 }
 
 pub(crate) fn find_max_iter(rootdir: &str, extsep: Option<&OsStr>) -> Result<u64, std::io::Error> {
-    let mut max_iter = 0;
-    let mut entries = std::fs::read_dir(rootdir)?
+    Ok(find_last(Path::new(rootdir), extsep)?.map_or(0, |(max_iter, _)| max_iter))
+}
+
+/// Find the numbered entry of `dir` with the largest number, along with its path.
+///
+/// If `extsep` is `None`, only directories are considered, otherwise only files
+/// with that extension.
+pub(crate) fn find_last(
+    dir: &Path,
+    extsep: Option<&OsStr>,
+) -> Result<Option<(u64, PathBuf)>, std::io::Error> {
+    let last = std::fs::read_dir(dir)?
         .filter_map(|entry| entry.ok()) // remove errors
-        .filter(|entry| {
-            if extsep.is_none() {
-                // if no extension, then only directories
-                entry.path().is_dir()
-            } else {
-                // if extension is given, then only files with that extension
-                entry.path().is_file() && entry.path().extension() == extsep
-            }
+        .map(|entry| entry.path())
+        .filter(|path| match extsep {
+            // if no extension, then only directories
+            None => path.is_dir(),
+            // if extension is given, then only files with that extension
+            Some(_) => path.is_file() && path.extension() == extsep,
         })
-        .filter_map(|entry| {
-            entry
-                .path()
-                .file_stem() // get the file stem, which is the name without the extension
-                .and_then(|x| x.to_owned().into_string().ok()) // convert to string
+        .filter_map(|path| {
+            // parse the file stem, which is the name without the extension, as a u64
+            let number = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+            Some((number, path))
         })
-        .filter_map(|x| x.parse::<_>().ok()) // parse the string as a u64
-        .collect::<Vec<_>>(); // collect the results into a vector
-    if entries.is_empty() {
-        // if no entries found
-        // return 0
-        return Ok(0);
+        .max_by_key(|(number, _)| *number);
+    Ok(last)
+}
+
+/// Select the run directory of an ExecCount store in `rootdir`.
+///
+/// # Returns:
+/// - `Ok((PathBuf, bool))` with the run directory, and whether it already exists.
+/// - `Err(std::io::Error)` if there was an error during the process.
+pub(crate) fn run_dir(rootdir: &str, run: Run) -> Result<(PathBuf, bool), std::io::Error> {
+    let latest = find_max_iter(rootdir, None)?.max(find_max_iter(rootdir, Some(OsStr::new("gz")))?);
+    let runcount = match run {
+        Run::Latest if latest > 0 => latest as u32,
+        Run::Id(runcount) => runcount,
+        _ => (latest as u32).checked_add(1).ok_or({
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Failed to increment run count",
+            )
+        })?,
+    };
+    let run_dir = PathBuf::from(rootdir).join(format!("{runcount:0>10}"));
+    let exists = run_dir.is_dir();
+    Ok((run_dir, exists))
+}
+
+/// Elapsed time of a resumed run, given the elapsed time at the start of the time slot
+/// of its latest file, `last`.
+///
+/// The elapsed time is taken from the run manifest, see [RunManifest::resumed_elapsed].
+/// If the manifest has neither anchors nor elapsed times of files, the time since `last`
+/// was last modified is added instead, which gives a lower bound of the time elapsed
+/// since the beginning of the run.
+pub(crate) fn resumed_elapsed(manifest: &RunManifest, start: Duration, last: &Path) -> Duration {
+    if let Some(elapsed) = manifest.resumed_elapsed() {
+        return elapsed.max(start);
     }
-    // sort the entries in descending order
-    entries.sort();
-    // reverse the order to get the maximum
-    entries.reverse();
-    // get the maximum value
-    Ok(entries[0]) // Safety: entries is not empty
+    let idle = std::fs::metadata(last)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    start + idle.unwrap_or_default()
 }